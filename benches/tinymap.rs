@@ -0,0 +1,292 @@
+//! Benchmarks behind the performance choices in [`TinyMap`], without a benchmark harness.
+//! Run with `cargo bench`, or `cargo bench -- NAME` for just the benchmarks whose name
+//! contains `NAME`. Each time is the best of several runs, in nanoseconds per operation.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    hint::black_box,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Instant,
+};
+
+use based_num::{
+    TinyMap, TinyMapPool,
+    factor::{FactorMultiset, factorize},
+};
+
+/// The system allocator, counting each allocation.
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+/// How many times each benchmark is run, keeping the fastest.
+const RUNS: usize = 7;
+
+/// The fastest time per operation of `RUNS` runs of `f`, which does `ops` operations.
+fn time_per_op(ops: usize, mut f: impl FnMut()) -> f64 {
+    time_per_op_with_setup(ops, || (), |()| f())
+}
+
+/// Like [`time_per_op`], but `f` is given a fresh value from `setup` each run. Neither
+/// that nor dropping what `f` returns is timed.
+fn time_per_op_with_setup<T, R>(
+    ops: usize,
+    mut setup: impl FnMut() -> T,
+    mut f: impl FnMut(T) -> R,
+) -> f64 {
+    (0..RUNS)
+        .map(|_| {
+            let input = setup();
+            let start = Instant::now();
+            let output = black_box(f(input));
+            let elapsed = start.elapsed();
+            drop(output);
+            elapsed.as_secs_f64() * 1e9 / ops as f64
+        })
+        .fold(f64::INFINITY, f64::min)
+}
+
+/// Where in sorted `entries` `key` is or would be, counting the smaller keys without
+/// branching, as `TinyMap` does for short maps.
+fn linear_search(entries: &[(usize, u8)], key: &usize) -> Result<usize, usize> {
+    let idx = entries.iter().map(|(k, _)| usize::from(k < key)).sum();
+    match entries.get(idx) {
+        Some((k, _)) if k == key => Ok(idx),
+        _ => Err(idx),
+    }
+}
+
+/// Where in sorted `entries` `key` is or would be, as `TinyMap` does for long maps.
+fn binary_search(entries: &[(usize, u8)], key: &usize) -> Result<usize, usize> {
+    entries.binary_search_by_key(&key, |(k, _)| k)
+}
+
+/// A search of sorted entries for a key, giving where it is or would be.
+type Search = fn(&[(usize, u8)], &usize) -> Result<usize, usize>;
+
+/// `TinyMap::get` on sorted `entries`, with the given search.
+fn get(search: Search) -> impl Fn(&[(usize, u8)], &usize) -> Option<u8> {
+    move |entries, key| search(entries, key).ok().map(|i| entries[i].1)
+}
+
+/// `TinyMap::increment` on sorted `entries`, with the given search.
+fn increment(search: Search) -> impl Fn(&mut Vec<(usize, u8)>, usize) {
+    move |entries, key| match search(entries, &key) {
+        Ok(i) => entries[i].1 = entries[i].1.wrapping_add(1),
+        Err(i) => entries.insert(i, (key, 1)),
+    }
+}
+
+/// Why short maps are searched linearly, and where `LINEAR_SEARCH_MAX_LEN` comes from:
+/// `get` and `increment` of sorted `usize` keys of increasing lengths, searching linearly
+/// and by binary search, and `TinyMap::get` itself. `get` looks up every key, along with a
+/// missing key between each pair, and `increment` counts every key (which are all there).
+fn search() {
+    println!(
+        "search: length, get linear (ns), get binary (ns), increment linear (ns), increment binary (ns), TinyMap::get (ns)"
+    );
+    for len in (1..=32).chain([48, 64, 96, 128]) {
+        let mut entries: Vec<(usize, u8)> = (0..len).map(|k| (2 * k + 1, 0)).collect();
+        let map = TinyMap::<usize, u8, 3>::from_vec(entries.clone());
+        let keys: Vec<usize> = (0..=2 * len).collect();
+        let present: Vec<usize> = entries.iter().map(|&(k, _)| k).collect();
+        let rounds = 1_000_000 / keys.len() + 1;
+        let ops = rounds * keys.len();
+        let increment_rounds = 1_000_000 / present.len() + 1;
+        let increment_ops = increment_rounds * present.len();
+
+        let time_get = |search| {
+            let get = get(search);
+            time_per_op(ops, || {
+                for _ in 0..rounds {
+                    for key in &keys {
+                        black_box(get(black_box(&entries), key));
+                    }
+                }
+            })
+        };
+        let get_linear = time_get(linear_search);
+        let get_binary = time_get(binary_search);
+        let mut time_increment = |search| {
+            let increment = increment(search);
+            time_per_op(increment_ops, || {
+                for _ in 0..increment_rounds {
+                    for &key in &present {
+                        increment(black_box(&mut entries), key);
+                    }
+                }
+            })
+        };
+        let increment_linear = time_increment(linear_search);
+        let increment_binary = time_increment(binary_search);
+        let tiny_map_get = time_per_op(ops, || {
+            for _ in 0..rounds {
+                for key in &keys {
+                    black_box(black_box(&map).get(key));
+                }
+            }
+        });
+        println!(
+            "{len:>4} {get_linear:>8.2} {get_binary:>8.2} {increment_linear:>8.2} {increment_binary:>8.2} {tiny_map_get:>8.2}"
+        );
+    }
+}
+
+/// The prime factors of each number from 2 up to `n`, with multiplicity, in increasing order.
+fn prime_factors_below(n: usize) -> Vec<Vec<usize>> {
+    (2..n)
+        .map(|i| {
+            factorize(i)
+                .iter()
+                .flat_map(|(&p, &k)| std::iter::repeat_n(p, k.into()))
+                .collect()
+        })
+        .collect()
+}
+
+/// Counting prime factors into a factor multiset, one at a time, with
+/// [`TinyMap::modify_or_insert`] and [`TinyMap::increment`] against the `entry` chain
+/// they replace.
+fn upsert() {
+    let numbers = prime_factors_below(100_000);
+    let ops = numbers.iter().map(Vec::len).sum();
+
+    let entry = time_per_op(ops, || {
+        for factors in &numbers {
+            let mut map = FactorMultiset::new();
+            for &p in factors {
+                map.entry(p).and_modify(|k| *k += 1).or_insert(1);
+            }
+            black_box(map);
+        }
+    });
+    let modify_or_insert = time_per_op(ops, || {
+        for factors in &numbers {
+            let mut map = FactorMultiset::new();
+            for &p in factors {
+                map.modify_or_insert(p, |k| *k += 1, || 1);
+            }
+            black_box(map);
+        }
+    });
+    let increment = time_per_op(ops, || {
+        for factors in &numbers {
+            let mut map = FactorMultiset::new();
+            for &p in factors {
+                map.increment(p);
+            }
+            black_box(map);
+        }
+    });
+    println!("upsert: entry chain (ns), modify_or_insert (ns), increment (ns)");
+    println!("{entry:>8.2} {modify_or_insert:>8.2} {increment:>8.2}");
+}
+
+/// Creating and discarding many maps which spill onto the heap, each freeing its buffer
+/// against each returning it to a [`TinyMapPool`] for the next. Also counts allocations.
+fn pool() {
+    const MAPS: usize = 100_000;
+    const LEN: usize = 8;
+
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let fresh = time_per_op(MAPS, || {
+        for _ in 0..MAPS {
+            let mut map = TinyMap::<usize, u8, 3>::new();
+            for key in 0..LEN {
+                map.insert(key, 0);
+            }
+            black_box(&map);
+        }
+    });
+    let fresh_allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+
+    let mut pool = TinyMapPool::new();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let pooled = time_per_op(MAPS, || {
+        for _ in 0..MAPS {
+            let mut map = TinyMap::<usize, u8, 3>::new_in(&mut pool);
+            for key in 0..LEN {
+                map.insert(key, 0);
+            }
+            black_box(&map);
+            map.recycle_into(&mut pool);
+        }
+    });
+    let pooled_allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+
+    let per_map = |allocations| allocations as f64 / (RUNS * MAPS) as f64;
+    println!("pool: new (ns), new_in (ns), new (allocations), new_in (allocations), per map");
+    println!(
+        "{fresh:>8.2} {pooled:>8.2} {:>8.2} {:>8.4}",
+        per_map(fresh_allocations),
+        per_map(pooled_allocations)
+    );
+}
+
+/// Finishing factor multisets which were just built, as the clone sieve does, with
+/// [`TinyMap::finalize`] against the [`TinyMap::shrink_to_fit`] it replaces. Most are
+/// inline, and the rest are on the heap, some with spare capacity.
+fn finalize() {
+    let numbers = prime_factors_below(1_000_000);
+    let ops = numbers.len();
+    // Built in each run's setup rather than cloned, since clones have no spare capacity
+    let build = || -> Vec<FactorMultiset> {
+        numbers
+            .iter()
+            .map(|factors| {
+                let mut map = FactorMultiset::new();
+                factors.iter().for_each(|&p| map.increment(p));
+                map
+            })
+            .collect()
+    };
+    let spare = build()
+        .iter()
+        .filter(|map| !map.is_inline() && map.capacity() > map.len())
+        .count();
+
+    let shrink_to_fit = time_per_op_with_setup(ops, build, |mut maps| {
+        maps.iter_mut().for_each(TinyMap::shrink_to_fit);
+        maps
+    });
+    let finalize = time_per_op_with_setup(ops, build, |mut maps| {
+        maps.iter_mut().for_each(TinyMap::finalize);
+        maps
+    });
+    println!("finalize: shrink_to_fit (ns), finalize (ns), maps with spare capacity");
+    println!("{shrink_to_fit:>8.2} {finalize:>8.2} {spare:>8} of {ops}");
+}
+
+fn main() {
+    // Cargo passes --bench, which isn't a filter
+    let filter: Vec<String> = std::env::args()
+        .skip(1)
+        .filter(|arg| !arg.starts_with("--"))
+        .collect();
+    let benchmarks: [(&str, fn()); 4] = [
+        ("search", search),
+        ("upsert", upsert),
+        ("pool", pool),
+        ("finalize", finalize),
+    ];
+    for (name, benchmark) in benchmarks {
+        if filter.is_empty() || filter.iter().any(|f| name.contains(f.as_str())) {
+            benchmark();
+            println!();
+        }
+    }
+}
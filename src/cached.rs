@@ -0,0 +1,186 @@
+//! A [`TinyMap`] which keeps an aggregate of its values up to date as it changes,
+//! for maps whose aggregate is queried far more often than the map changes.
+
+use std::{
+    fmt::Debug,
+    ops::{Add, Div, Mul, Sub},
+};
+
+use crate::{CapacityPolicy, Doubling, TinyMap};
+
+/// An aggregate of the values of a map which can be updated one value at a time,
+/// in both directions - so that removing a value doesn't mean recomputing the
+/// aggregate from scratch. Used by [`CachedTinyMap`].
+pub trait Aggregate<V> {
+    /// The type of the aggregate.
+    type Output: Copy;
+
+    /// The aggregate of no values.
+    fn empty() -> Self::Output;
+
+    /// The aggregate with `value` added.
+    fn add(aggregate: Self::Output, value: &V) -> Self::Output;
+
+    /// The aggregate with `value` (which was previously added) taken back out.
+    fn remove(aggregate: Self::Output, value: &V) -> Self::Output;
+}
+
+/// The sum of the values.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Sum;
+
+impl<V: Copy + Default + Add<Output = V> + Sub<Output = V>> Aggregate<V> for Sum {
+    type Output = V;
+
+    fn empty() -> V {
+        V::default()
+    }
+
+    fn add(sum: V, value: &V) -> V {
+        sum + *value
+    }
+
+    fn remove(sum: V, value: &V) -> V {
+        sum - *value
+    }
+}
+
+/// The product of the values, which must all be non-zero - a zero can't be divided
+/// back out of the product.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Product;
+
+impl<V: Copy + From<u8> + Mul<Output = V> + Div<Output = V>> Aggregate<V> for Product {
+    type Output = V;
+
+    fn empty() -> V {
+        V::from(1)
+    }
+
+    fn add(product: V, value: &V) -> V {
+        product * *value
+    }
+
+    fn remove(product: V, value: &V) -> V {
+        product / *value
+    }
+}
+
+/// A [`TinyMap`] along with the aggregate `A` of its values, which is updated as
+/// entries are inserted, modified and removed - in `O(1)` per change, rather than the
+/// `O(n)` of recomputing it with e.g. [`TinyMap::sum_values`].
+///
+/// The aggregate is never stale: the map can only be changed through this type, and
+/// every method which changes it updates the aggregate too. Read-only access to the
+/// map itself is available through [`as_map`](Self::as_map).
+pub struct CachedTinyMap<
+    K: Default,
+    V: Default,
+    const N: usize,
+    A: Aggregate<V>,
+    P: CapacityPolicy = Doubling,
+> {
+    map: TinyMap<K, V, N, P>,
+    aggregate: A::Output,
+}
+
+impl<K: Default + Ord, V: Default, const N: usize, A: Aggregate<V>, P: CapacityPolicy>
+    CachedTinyMap<K, V, N, A, P>
+{
+    /// Creates a new empty map.
+    pub fn new() -> Self {
+        Self {
+            map: TinyMap::new(),
+            aggregate: A::empty(),
+        }
+    }
+
+    /// The aggregate of all of the values in the map.
+    pub fn aggregate(&self) -> A::Output {
+        self.aggregate
+    }
+
+    /// The map itself.
+    pub fn as_map(&self) -> &TinyMap<K, V, N, P> {
+        &self.map
+    }
+
+    /// Consumes the wrapper, returning the map.
+    pub fn into_map(self) -> TinyMap<K, V, N, P> {
+        self.map
+    }
+
+    /// The value at the given key, if it exists.
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.map.get(key)
+    }
+
+    /// Inserts a key-value pair into the map, returning the old value at the key,
+    /// if there was one. See [`TinyMap::insert`].
+    pub fn insert(&mut self, key: K, val: V) -> Option<V> {
+        self.aggregate = A::add(self.aggregate, &val);
+        let old = self.map.insert(key, val);
+        if let Some(old) = &old {
+            self.aggregate = A::remove(self.aggregate, old);
+        }
+        old
+    }
+
+    /// Removes the entry at the given key, returning its value, if it exists.
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let idx = self.map.search(key).ok()?;
+        let (_, val) = self.map.remove_index(idx);
+        self.aggregate = A::remove(self.aggregate, &val);
+        Some(val)
+    }
+
+    /// Applies `modify` to the value at the given key if it exists, or otherwise
+    /// inserts the value returned by `insert`. See [`TinyMap::modify_or_insert`].
+    pub fn modify_or_insert(
+        &mut self,
+        key: K,
+        modify: impl FnOnce(&mut V),
+        insert: impl FnOnce() -> V,
+    ) {
+        match self.map.search(&key) {
+            Ok(idx) => {
+                let val = &mut self.map.inner[idx].1;
+                self.aggregate = A::remove(self.aggregate, val);
+                modify(val);
+                self.aggregate = A::add(self.aggregate, val);
+            }
+            Err(_) => {
+                let val = insert();
+                self.aggregate = A::add(self.aggregate, &val);
+                self.map.insert(key, val);
+            }
+        }
+    }
+
+    /// Removes all entries.
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.aggregate = A::empty();
+    }
+}
+
+impl<K: Default + Ord, V: Default, const N: usize, A: Aggregate<V>, P: CapacityPolicy> Default
+    for CachedTinyMap<K, V, N, A, P>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Default + Debug, V: Default + Debug, const N: usize, A: Aggregate<V>, P: CapacityPolicy>
+    Debug for CachedTinyMap<K, V, N, A, P>
+where
+    A::Output: Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CachedTinyMap")
+            .field("map", &self.map)
+            .field("aggregate", &self.aggregate)
+            .finish()
+    }
+}
@@ -0,0 +1,244 @@
+//! Prime factorizations, and the arithmetic functions derived from them.
+
+use crate::{TinyMap, cached::Aggregate};
+
+/// A multiset of prime factors. Represented as a map of Prime -> Power.
+/// Backing storage of `TinyMap` ensures that as long as there are 3 or fewer
+/// prime factors for a number (which is true for ~62% of numbers),
+/// this will not need to allocate. To reduce allocations more, increase
+/// the size of the array part of the `TinyMap` - doing this will increase the
+/// amount of memory used for numbers with fewer than that many factors.
+/// For a backing storage array size of 3, there will not be any need for allocation
+/// for ~62% of numbers, but the average amount of memory used will be increased by
+/// ~22%. Numbers which do spill onto the heap waste some of their heap buffer to
+/// doubling growth - a `TinyMap<usize, u8, 3, Exact>` (see [`Exact`](crate::Exact))
+/// avoids this, at the cost of a reallocation for every prime factor past the third.
+pub type FactorMultiset = TinyMap<usize, u8, 3>;
+
+/// Factorizes `n` by trial division by `primes`, which must contain (in order)
+/// at least every prime no larger than `sqrt(n)`.
+pub fn factorize_with_primes(mut n: usize, primes: &[usize]) -> FactorMultiset {
+    let mut factors = FactorMultiset::new();
+
+    for &p in primes {
+        if p * p > n {
+            break;
+        }
+        while n.is_multiple_of(p) {
+            factors.increment(p);
+            n /= p;
+        }
+    }
+
+    // Whatever is left over has no prime factors no larger than its square root,
+    // so it must itself be prime
+    if n > 1 {
+        factors.increment(n);
+    }
+
+    factors
+}
+
+/// Factorizes `n` by trial division. Good for one-off factorizations - for
+/// factorizing many numbers, prefer a sieve.
+pub fn factorize(n: usize) -> FactorMultiset {
+    let mut factors = FactorMultiset::new();
+    let mut n = n;
+    let mut p = 2;
+
+    while p * p <= n {
+        while n.is_multiple_of(p) {
+            factors.increment(p);
+            n /= p;
+        }
+        p += 1;
+    }
+
+    if n > 1 {
+        factors.increment(n);
+    }
+
+    factors
+}
+
+/// d(n), the number of divisors of the number with prime factorization `factors`.
+pub fn num_factors(factors: &FactorMultiset) -> u64 {
+    // Each divisor picks a power from 0 to k for each prime factor
+    factors.product_values(|&k| u64::from(k) + 1)
+}
+
+/// d(n) as an [`Aggregate`] of the powers of a factor multiset, for keeping the number
+/// of divisors up to date in a [`CachedTinyMap`](crate::cached::CachedTinyMap) as factors
+/// are multiplied in and divided out.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NumFactors;
+
+impl Aggregate<u8> for NumFactors {
+    type Output = u64;
+
+    fn empty() -> u64 {
+        1
+    }
+
+    fn add(d: u64, &k: &u8) -> u64 {
+        d * (u64::from(k) + 1)
+    }
+
+    fn remove(d: u64, &k: &u8) -> u64 {
+        d / (u64::from(k) + 1)
+    }
+}
+
+/// σ(n), the sum of the divisors of the number with prime factorization `factors`.
+pub fn sum_of_divisors(factors: &FactorMultiset) -> u64 {
+    // σ is multiplicative, and σ(p^k) = 1 + p + ... + p^k
+    factors
+        .iter()
+        .map(|(&p, &k)| (0..=k).map(|j| (p as u64).pow(j.into())).sum::<u64>())
+        .product()
+}
+
+/// φ(n), Euler's totient - how many numbers from 1 to n are coprime to n - of the number
+/// with prime factorization `factors`.
+pub fn totient(factors: &FactorMultiset) -> u64 {
+    // φ is multiplicative, and φ(p^k) = p^(k - 1) · (p - 1)
+    factors
+        .iter()
+        .map(|(&p, &k)| (p as u64).pow(u32::from(k) - 1) * (p as u64 - 1))
+        .product()
+}
+
+/// μ(n), the Möbius function of the number with prime factorization `factors`: 0 if
+/// n has a repeated prime factor, and otherwise 1 or -1 for an even or odd number of
+/// prime factors.
+pub fn mobius(factors: &FactorMultiset) -> i8 {
+    if factors.values().any(|&k| k > 1) {
+        0
+    } else if factors.len().is_multiple_of(2) {
+        1
+    } else {
+        -1
+    }
+}
+
+/// The abundancy σ(n) / n of a number `n`, as an exact fraction. Abundancies
+/// are compared exactly, without any floating point.
+#[derive(Clone, Copy, Debug)]
+pub struct Abundancy {
+    /// σ(n), the sum of the divisors of `n`.
+    pub sum_of_divisors: u64,
+    /// The number `n` itself.
+    pub number: u64,
+}
+
+impl Abundancy {
+    /// Whether or not `n` is a perfect number - that is, σ(n) = 2n.
+    pub fn is_perfect(&self) -> bool {
+        u128::from(self.sum_of_divisors) == 2 * u128::from(self.number)
+    }
+}
+
+impl Ord for Abundancy {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        // a / b < c / d if and only if a * d < c * b, for positive b and d
+        (u128::from(self.sum_of_divisors) * u128::from(other.number))
+            .cmp(&(u128::from(other.sum_of_divisors) * u128::from(self.number)))
+    }
+}
+
+impl PartialOrd for Abundancy {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Abundancy {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for Abundancy {}
+
+/// All divisors of the number with prime factorization `factors`, in increasing order.
+pub fn divisors(factors: &FactorMultiset) -> Vec<u64> {
+    let mut divisors = Vec::with_capacity(num_factors(factors) as usize);
+    divisors.push(1);
+
+    // Every divisor is a product of prime powers p^j for 0 <= j <= k
+    for (&p, &k) in factors.iter() {
+        let smaller = divisors.len();
+        let mut power = 1;
+        for _ in 0..k {
+            power *= p as u64;
+            for i in 0..smaller {
+                divisors.push(divisors[i] * power);
+            }
+        }
+    }
+
+    divisors.sort_unstable();
+    divisors
+}
+
+/// A few numbers whose factorizations, d(n), ω(n) and basedness are easy to check by hand,
+/// for use as fixtures: 1, a prime (97), a prime power (2^7), a squarefree composite
+/// (2 · 3 · 5 · 7) and a highly composite number (5040 = 2^4 · 3^2 · 5 · 7).
+pub const FIXTURE_NUMBERS: [usize; 5] = [1, 97, 128, 210, 5040];
+
+/// The basedness of `n`: ω(n) · d(n - 1), where ω(n) is the number of distinct
+/// prime factors of `n`, and d(n) is the number of divisors of `n`.
+pub fn basedness(n: usize) -> u64 {
+    if n <= 1 {
+        // ω(1) = 0
+        return 0;
+    }
+
+    factorize(n).len() as u64 * num_factors(&factorize(n - 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oracle;
+
+    /// The primes below `n`, for trial division.
+    fn primes_below(n: usize) -> Vec<usize> {
+        (2..n).filter(|&p| oracle::is_prime(p)).collect()
+    }
+
+    fn pairs(factors: &FactorMultiset) -> Vec<(usize, u8)> {
+        factors.iter().map(|(&p, &k)| (p, k)).collect()
+    }
+
+    #[test]
+    fn factorize_matches_oracle() {
+        let primes = primes_below(1000);
+        for n in 1..1000 {
+            assert_eq!(pairs(&factorize(n)), oracle::factorization(n), "n = {n}");
+            assert_eq!(
+                pairs(&factorize_with_primes(n, &primes)),
+                oracle::factorization(n),
+                "n = {n}"
+            );
+        }
+    }
+
+    #[test]
+    fn arithmetic_functions_match_oracle() {
+        for n in 1..1000 {
+            let factors = factorize(n);
+            assert_eq!(num_factors(&factors), oracle::num_factors(n), "d({n})");
+            assert_eq!(
+                sum_of_divisors(&factors),
+                oracle::sum_of_divisors(n),
+                "σ({n})"
+            );
+            assert_eq!(factors.len() as u64, oracle::num_prime_factors(n), "ω({n})");
+            assert_eq!(totient(&factors), oracle::totient(n), "φ({n})");
+            assert_eq!(mobius(&factors), oracle::mobius(n), "μ({n})");
+            assert_eq!(divisors(&factors), oracle::divisors(n), "divisors of {n}");
+            assert_eq!(basedness(n), oracle::basedness(n), "basedness of {n}");
+        }
+    }
+}
@@ -1,23 +1,98 @@
+pub mod cached;
+pub mod factor;
+pub mod records;
+pub mod sieve;
+
+#[cfg(test)]
+mod oracle;
+
+use std::{
+    cmp::Ordering,
+    collections::BTreeMap,
+    fmt::Debug,
+    iter::{Product, Sum},
+    marker::PhantomData,
+    ops::{AddAssign, SubAssign},
+};
+
 use delegate::delegate;
-use tinyvec::TinyVec;
+use tinyvec::{Array, ArrayVec, TinyVec};
+
+/// Maps no longer than this are searched linearly, rather than by binary search.
+/// `cargo bench -- search` compares `get` and `increment` with each search over `usize`
+/// keys. Up to a length of about 12 the two have been within a nanosecond or so of each
+/// other, less than the noise between runs, and binary search is clearly faster past
+/// about 24. This is somewhere in between, rather than a tuned crossover.
+const LINEAR_SEARCH_MAX_LEN: usize = 16;
 
 /// A binary tree map with backing storage of a [`TinyVec`].
-#[derive(Clone, Debug, Default)]
-pub struct TinyMap<K: Default, V: Default, const N: usize> {
+///
+/// How the backing storage grows once it runs out of room is chosen by the
+/// [`CapacityPolicy`] `P` - see [`Doubling`] (the default) and [`Exact`].
+#[derive(Clone, Default)]
+pub struct TinyMap<K: Default, V: Default, const N: usize, P: CapacityPolicy = Doubling> {
     inner: TinyVec<[(K, V); N]>,
+    policy: PhantomData<P>,
 }
 
-impl<K: Default, V: Default, const N: usize> TinyMap<K, V, N> {
+impl<K: Default, V: Default, const N: usize, P: CapacityPolicy> TinyMap<K, V, N, P> {
     /// Creates a new empty [`TinyMap`].
     pub fn new() -> Self {
         Self {
             inner: TinyVec::new(),
+            policy: PhantomData,
+        }
+    }
+
+    /// Creates a new empty [`TinyMap`] with room for at least `capacity` elements. If
+    /// `capacity` is at most `N`, this is the same as [`new`](Self::new), and the map
+    /// starts out inline. Otherwise, it starts out on the heap.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            inner: TinyVec::with_capacity(capacity),
+            policy: PhantomData,
+        }
+    }
+
+    /// Creates a new empty [`TinyMap`] in a `const` context, e.g. for a `static`, with
+    /// `data` as its inline storage. The contents of `data` are just placeholders, which
+    /// are overwritten as entries are inserted - e.g. `TinyMap::from_array_empty([(0, 0); 3])`
+    /// is an empty [`FactorMultiset`](factor::FactorMultiset).
+    ///
+    /// [`new`](Self::new) can't be `const` itself, since it fills the inline storage with
+    /// `Default::default()`, which isn't callable in a `const` context.
+    pub const fn from_array_empty(data: [(K, V); N]) -> Self {
+        Self {
+            inner: TinyVec::Inline(ArrayVec::from_array_empty(data)),
+            policy: PhantomData,
+        }
+    }
+
+    /// Creates a new empty [`TinyMap`], reusing a heap buffer from `pool` if there
+    /// is one, rather than starting out inline.
+    pub fn new_in(pool: &mut TinyMapPool<K, V>) -> Self {
+        match pool.buffers.pop() {
+            Some(buffer) => Self {
+                inner: TinyVec::Heap(buffer),
+                policy: PhantomData,
+            },
+            None => Self::new(),
+        }
+    }
+
+    /// Clears the map, returning its heap buffer (if it has one) to `pool` for reuse by
+    /// [`new_in`](Self::new_in) rather than de-allocating it. The map is left empty and inline.
+    pub fn recycle_into(&mut self, pool: &mut TinyMapPool<K, V>) {
+        if let TinyVec::Heap(mut buffer) = std::mem::take(&mut self.inner) {
+            buffer.clear();
+            pool.buffers.push(buffer);
         }
     }
 
     delegate! {
         to self.inner {
-            /// The capacity of the internal backing storage.
+            /// The capacity of the internal backing storage. For an [inline](Self::is_inline)
+            /// map, this is always `N`.
             pub fn capacity(&self) -> usize;
 
             /// Remove all elements.
@@ -26,9 +101,25 @@ impl<K: Default, V: Default, const N: usize> TinyMap<K, V, N> {
             /// Whether or not the map is empty.
             pub fn is_empty(&self) -> bool;
 
+            /// Whether or not the map is currently stored inline (in its array of `N`
+            /// elements), rather than on the heap. Useful for measuring how often a
+            /// choice of `N` avoids allocation.
+            pub fn is_inline(&self) -> bool;
+
             /// The length of the map (in no. of elements)
             pub fn len(&self) -> usize;
 
+            /// Reserve capacity for at least `additional` more elements. If the map is stored
+            /// inline and the extra elements still fit in `N`, this does nothing, and the map
+            /// stays inline. Otherwise, the map moves to (or grows on) the heap.
+            pub fn reserve(&mut self, additional: usize);
+
+            /// Reserve capacity for exactly `additional` more elements, without the
+            /// over-allocation that amortized growth would do. If the map is stored inline
+            /// and the extra elements still fit in `N`, this does nothing. Otherwise, the map
+            /// moves to (or grows on) the heap.
+            pub fn reserve_exact(&mut self, additional: usize);
+
             /// Shrink the capacity of the map as much as possible. This can
             /// cause the backing storage [`TinyVec`] to de-allocate and "inline"
             /// itself if the resulting capacity is less than or equal to `N`.
@@ -36,25 +127,348 @@ impl<K: Default, V: Default, const N: usize> TinyMap<K, V, N> {
         }
     }
 
+    /// How many more elements fit in the backing storage before it has to grow. For an
+    /// [inline](Self::is_inline) map, this is how many more elements can be inserted
+    /// without spilling onto the heap.
+    pub fn spare_capacity(&self) -> usize {
+        self.capacity() - self.len()
+    }
+
+    /// Shrink the capacity of the map, but to no less than `min_capacity`
+    /// (or the length of the map, whichever is larger).
+    ///
+    /// If `min_capacity` is less than or equal to `N`, this is the same as
+    /// [`shrink_to_fit`](Self::shrink_to_fit), since the inline storage already
+    /// has room for `N` elements. Otherwise, inline storage is left untouched,
+    /// and heap storage stays on the heap.
+    pub fn shrink_to(&mut self, min_capacity: usize) {
+        match &mut self.inner {
+            _ if min_capacity <= N => self.inner.shrink_to_fit(),
+            TinyVec::Heap(vec) => vec.shrink_to(min_capacity),
+            TinyVec::Inline(_) => {}
+        }
+    }
+
+    /// Like [`shrink_to_fit`](Self::shrink_to_fit), for a map which is done being built,
+    /// but only touches the backing storage if there is anything to shrink: a heap buffer
+    /// with unused capacity, or one which now fits inline. Inline maps, which are most
+    /// small maps, are left alone without a call into [`TinyVec`].
+    #[inline]
+    pub fn finalize(&mut self) {
+        if let TinyVec::Heap(vec) = &self.inner
+            && (vec.capacity() > vec.len() || vec.len() <= N)
+        {
+            self.inner.shrink_to_fit();
+        }
+    }
+
+    /// An iterator over the key-value pairs contained in the map, in sorted order.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&K, &V)> + ExactSizeIterator {
+        self.inner.iter().map(|(k, v)| (k, v))
+    }
+
+    /// An iterator over the key-value pairs contained in the map, in sorted order, with
+    /// mutable access to the values. Like every method which hands out mutable access,
+    /// only the values are mutable - mutating a key could leave the keys out of order.
+    pub fn iter_mut(
+        &mut self,
+    ) -> impl DoubleEndedIterator<Item = (&K, &mut V)> + ExactSizeIterator {
+        self.inner.iter_mut().map(|(k, v)| (&*k, v))
+    }
+
+    /// The entry with the largest key satisfying `pred`, if any. Searches from the back,
+    /// so it stops as soon as it finds one - e.g. for the largest prime factor below
+    /// some bound, without looking at the smaller prime factors.
+    pub fn last_key_matching(&self, mut pred: impl FnMut(&K) -> bool) -> Option<(&K, &V)> {
+        self.iter().rev().find(|(k, _)| pred(k))
+    }
+
+    /// The entry with the smallest key, along with the rest of the entries (in sorted order).
+    #[allow(clippy::type_complexity)]
+    pub fn split_first(&self) -> Option<((&K, &V), &[(K, V)])> {
+        self.inner
+            .split_first()
+            .map(|((k, v), rest)| ((k, v), rest))
+    }
+
+    /// The entry with the largest key, along with the rest of the entries (in sorted order).
+    #[allow(clippy::type_complexity)]
+    pub fn split_last(&self) -> Option<((&K, &V), &[(K, V)])> {
+        self.inner.split_last().map(|((k, v), rest)| ((k, v), rest))
+    }
+
+    /// Removes and returns the entry at index `idx` of the (sorted) backing storage,
+    /// shifting all entries after it down by one to keep the map sorted. Indices
+    /// are those given by [`iter_indexed`](Self::iter_indexed).
+    ///
+    /// # Panics
+    ///
+    /// If `idx` is out of bounds.
+    pub fn remove_index(&mut self, idx: usize) -> (K, V) {
+        self.inner.remove(idx)
+    }
+
+    /// The entry at index `idx` of the (sorted) backing storage - that is, the entry
+    /// with the `idx`th smallest key, counting from zero - or `None` if `idx` is out
+    /// of bounds. E.g. the `k`th smallest prime factor, without iterating.
+    pub fn get_index(&self, idx: usize) -> Option<(&K, &V)> {
+        self.inner.get(idx).map(|(k, v)| (k, v))
+    }
+
+    /// The key of the entry at index `idx`, as in [`get_index`](Self::get_index).
+    pub fn key_at(&self, idx: usize) -> Option<&K> {
+        self.get_index(idx).map(|(k, _)| k)
+    }
+
+    /// The value of the entry at index `idx`, as in [`get_index`](Self::get_index).
+    pub fn value_at(&self, idx: usize) -> Option<&V> {
+        self.get_index(idx).map(|(_, v)| v)
+    }
+
+    /// Keeps only the entries for which `f` returns `true`, in place. The remaining
+    /// entries stay sorted.
+    pub fn retain(&mut self, mut f: impl FnMut(&K, &V) -> bool) {
+        self.inner.retain(|(k, v)| f(k, v));
+    }
+
+    /// Like [`retain`](Self::retain), but returns how many entries were removed.
+    pub fn retain_count(&mut self, f: impl FnMut(&K, &V) -> bool) -> usize {
+        let len = self.len();
+        self.retain(f);
+        len - self.len()
+    }
+
+    /// Replaces the value of each entry with `f` of its key and old value, in place, or
+    /// removes the entry if `f` returns `None` - e.g. `|_, k| k.checked_sub(1).filter(|&k| k > 0)`
+    /// divides a factor multiset by its radical. The remaining entries stay sorted. Each old
+    /// value is moved out with [`std::mem::take`], as in [`TinyMapEntry::and_replace`].
+    pub fn retain_filter_map(&mut self, mut f: impl FnMut(&K, V) -> Option<V>) {
+        self.inner
+            .retain_mut(|(k, v)| match f(k, std::mem::take(v)) {
+                Some(new) => {
+                    *v = new;
+                    true
+                }
+                None => false,
+            });
+    }
+
+    /// An iterator which removes and yields (in sorted order) every entry for which
+    /// `pred` returns `true`, leaving the other entries in place. The remaining entries
+    /// stay sorted. Entries which haven't been visited when the iterator is dropped are
+    /// left in the map.
+    pub fn extract_if<'a>(
+        &'a mut self,
+        mut pred: impl FnMut(&K, &mut V) -> bool + 'a,
+    ) -> impl Iterator<Item = (K, V)> + 'a {
+        let mut idx = 0;
+        std::iter::from_fn(move || {
+            while idx < self.inner.len() {
+                let (k, v) = &mut self.inner[idx];
+                if pred(k, v) {
+                    return Some(self.inner.remove(idx));
+                }
+                idx += 1;
+            }
+            None
+        })
+    }
+
+    /// An iterator over the key-value pairs contained in the map, in sorted order,
+    /// along with the index of each pair in the backing storage.
+    pub fn iter_indexed(
+        &self,
+    ) -> impl DoubleEndedIterator<Item = (usize, &K, &V)> + ExactSizeIterator {
+        self.inner.iter().enumerate().map(|(i, (k, v))| (i, k, v))
+    }
+
+    /// An iterator over the keys contained in the map, in sorted order.
+    pub fn keys(&self) -> impl DoubleEndedIterator<Item = &K> + ExactSizeIterator {
+        self.inner.iter().map(|(k, _)| k)
+    }
+
     /// An iterator over the values contained in the map.
-    pub fn values(&self) -> impl Iterator<Item = &V> {
+    pub fn values(&self) -> impl DoubleEndedIterator<Item = &V> + ExactSizeIterator {
         self.inner.iter().map(|(_, v)| v)
     }
+
+    /// A mutable iterator over the values contained in the map, in order of their keys.
+    pub fn values_mut(&mut self) -> impl DoubleEndedIterator<Item = &mut V> + ExactSizeIterator {
+        self.inner.iter_mut().map(|(_, v)| v)
+    }
+
+    /// Folds every value into an accumulator, in order of their keys.
+    pub fn fold_values<B>(&self, init: B, f: impl FnMut(B, &V) -> B) -> B {
+        self.values().fold(init, f)
+    }
+
+    /// The sum of `f` applied to every value - e.g. Ω(n), the number of prime factors of
+    /// `n` counted with multiplicity, is `sum_values(|&k| u64::from(k))` of its factorization.
+    pub fn sum_values<T: Sum>(&self, f: impl FnMut(&V) -> T) -> T {
+        self.values().map(f).sum()
+    }
+
+    /// The product of `f` applied to every value - e.g. d(n), the number of divisors of
+    /// `n`, is `product_values(|&k| u64::from(k) + 1)` of its factorization.
+    pub fn product_values<T: Product>(&self, f: impl FnMut(&V) -> T) -> T {
+        self.values().map(f).product()
+    }
+
+    /// Reduces the values to a single one by repeatedly applying `f`, in order of
+    /// their keys. Returns `None` if the map is empty.
+    pub fn reduce_values(&self, f: impl FnMut(V, V) -> V) -> Option<V>
+    where
+        V: Clone,
+    {
+        self.values().cloned().reduce(f)
+    }
+
+    /// Consumes the map, returning an iterator over its keys in sorted order.
+    pub fn into_keys(self) -> impl DoubleEndedIterator<Item = K> + ExactSizeIterator {
+        self.inner.into_iter().map(|(k, _)| k)
+    }
+
+    /// Consumes the map, returning an iterator over its values, in order of their keys.
+    pub fn into_values(self) -> impl DoubleEndedIterator<Item = V> + ExactSizeIterator {
+        self.inner.into_iter().map(|(_, v)| v)
+    }
+
+    /// Consumes the map, splitting it into the entries whose keys satisfy `pred`, and
+    /// the rest. Since each half keeps its entries in their original order, both are
+    /// still sorted - e.g. a factor multiset can be split into its small and large primes.
+    pub fn partition(self, mut pred: impl FnMut(&K) -> bool) -> (Self, Self) {
+        let (mut matching, mut rest) = (Self::new(), Self::new());
+        for entry in self.inner {
+            let half = if pred(&entry.0) {
+                &mut matching
+            } else {
+                &mut rest
+            };
+            P::make_room(&mut half.inner);
+            half.inner.push(entry);
+        }
+        (matching, rest)
+    }
+
+    /// Consumes the map, relabelling each key with `f`, which must be strictly increasing -
+    /// that is, it must map larger keys to larger keys - so that the keys stay sorted
+    /// without sorting them again. This is checked in debug builds only.
+    pub fn map_keys_monotonic<L: Default + Ord>(
+        self,
+        mut f: impl FnMut(K) -> L,
+    ) -> TinyMap<L, V, N, P> {
+        let map = TinyMap {
+            inner: self.inner.into_iter().map(|(k, v)| (f(k), v)).collect(),
+            policy: PhantomData,
+        };
+        map.debug_assert_invariants();
+        map
+    }
+
+    /// Consumes the map, returning its key-value pairs in sorted order. Reuses the
+    /// heap buffer if the map has one.
+    pub fn into_vec(self) -> Vec<(K, V)> {
+        self.inner.into_vec()
+    }
+
+    /// A sorted copy of the map's key-value pairs, leaving the map as it is - unlike
+    /// [`into_vec`](Self::into_vec). Useful for logging or comparing snapshots of a map.
+    pub fn to_vec(&self) -> Vec<(K, V)>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        self.inner.to_vec()
+    }
+
+    /// Inserts `entry` at `idx` in the backing storage, growing it according to the
+    /// capacity policy if it is full. Callers are responsible for keeping the keys sorted.
+    fn insert_at(&mut self, idx: usize, entry: (K, V)) {
+        P::make_room(&mut self.inner);
+        self.inner.insert(idx, entry);
+    }
 }
 
-impl<K: Default + Ord, V: Default, const N: usize> TinyMap<K, V, N> {
+impl<K: Default + Ord, V: Default, const N: usize, P: CapacityPolicy> TinyMap<K, V, N, P> {
     /// Returns a symbolic "entry" value corresponding to the given key,
     /// which enables in-place modification and/or delayed insertion of
     /// a new element at that key.
-    pub fn entry(&mut self, key: K) -> TinyMapEntry<K, V, N> {
-        match self.inner.binary_search_by_key(&&key, |(key, _)| key) {
-            Ok(idx) => TinyMapEntry::Occupied(&mut self.inner[idx]),
-            Err(idx) => TinyMapEntry::Vacant {
-                inner: &mut self.inner,
+    pub fn entry(&mut self, key: K) -> TinyMapEntry<'_, K, V, N, P> {
+        match self.search(&key) {
+            Ok(idx) => TinyMapEntry::Occupied(OccupiedEntry { map: self, idx }),
+            Err(idx) => TinyMapEntry::Vacant(VacantEntry {
+                map: self,
                 key,
                 idx,
+            }),
+        }
+    }
+
+    /// Creates a map from key-value pairs in any order. For duplicate keys, the last
+    /// value wins, as with [`Extend`]. Sorts once rather than searching for each pair, and
+    /// reuses the vector's buffer if the map doesn't fit inline.
+    pub fn from_vec(mut vec: Vec<(K, V)>) -> Self {
+        // Stable, so that the last value for a key is at the end of its run
+        vec.sort_by(|(a, _), (b, _)| a.cmp(b));
+        // `dedup_by` keeps the first of each run, so move later values into it
+        vec.dedup_by(|(later_key, later), (key, kept)| {
+            let duplicate = later_key == key;
+            if duplicate {
+                std::mem::swap(later, kept);
+            }
+            duplicate
+        });
+
+        let map = Self {
+            inner: if vec.len() <= N {
+                vec.into_iter().collect()
+            } else {
+                TinyVec::Heap(vec)
             },
+            policy: PhantomData,
+        };
+        map.debug_assert_invariants();
+        map
+    }
+
+    /// Creates a map from key-value pairs in any order, like [`from_vec`](Self::from_vec),
+    /// except that the values of duplicate keys are summed rather than the last one
+    /// winning - e.g. a factor multiset from a stream of `(prime, 1)` pairs.
+    pub fn from_iter_summing(iter: impl IntoIterator<Item = (K, V)>) -> Self
+    where
+        V: AddAssign,
+    {
+        let mut map = Self::new();
+        for (key, val) in iter {
+            map.add_count(key, val);
         }
+        map
+    }
+
+    /// The value at the given key, if it exists.
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.search(key).ok().map(|i| &self.inner[i].1)
+    }
+
+    /// A mutable reference to the value at the given key, if it exists.
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.search(key).ok().map(|i| &mut self.inner[i].1)
+    }
+
+    /// A copy of the value at the given key, or `V::default()` if it doesn't exist.
+    /// Unlike inserting through an entry, this does not modify the map.
+    pub fn get_copy_or_default(&self, key: &K) -> V
+    where
+        V: Copy,
+    {
+        self.get(key).copied().unwrap_or_default()
+    }
+
+    /// The index of the given key in the (sorted) backing storage - that is, how many
+    /// keys are smaller than it - or `None` if it doesn't exist. The inverse of
+    /// [`key_at`](Self::key_at).
+    pub fn position_of(&self, key: &K) -> Option<usize> {
+        self.search(key).ok()
     }
 
     /// Inserts a key-value pair into the map.
@@ -63,56 +477,967 @@ impl<K: Default + Ord, V: Default, const N: usize> TinyMap<K, V, N> {
     ///
     /// If the map did have this key present, the value is updated, and the old value is returned.
     pub fn insert(&mut self, key: K, val: V) -> Option<V> {
-        match self.inner.binary_search_by_key(&&key, |(key, _)| key) {
+        match self.search(&key) {
             Ok(i) => Some(std::mem::replace(&mut self.inner[i].1, val)),
             Err(i) => {
-                self.inner.insert(i, (key, val));
+                self.insert_at(i, (key, val));
+                self.debug_assert_invariants();
                 None
             }
         }
     }
+
+    /// Applies `modify` to the value at the given key if it exists, or otherwise
+    /// inserts the value returned by `insert`. Like
+    /// `entry(key).and_modify(modify).or_insert(insert())` in a single call, except
+    /// that `insert` is only called if it is needed.
+    pub fn modify_or_insert(
+        &mut self,
+        key: K,
+        modify: impl FnOnce(&mut V),
+        insert: impl FnOnce() -> V,
+    ) {
+        match self.search(&key) {
+            Ok(i) => modify(&mut self.inner[i].1),
+            Err(i) => {
+                self.insert_at(i, (key, insert()));
+                self.debug_assert_invariants();
+            }
+        }
+    }
+
+    /// Adds one to the value at the given key, or inserts a value of one if the key isn't
+    /// present - the common `entry(key).and_modify(|k| *k += 1).or_insert(1)` of counting,
+    /// such as counting the powers of a factor multiset, in a single call. Like that chain,
+    /// it searches for the key once; `cargo bench -- upsert` compares the two.
+    pub fn increment(&mut self, key: K)
+    where
+        V: From<u8> + AddAssign,
+    {
+        self.modify_or_insert(key, |v| *v += V::from(1), || V::from(1));
+    }
+
+    /// Adds `delta` to the count at the given key, inserting `delta` as the count if the
+    /// key isn't present. With [`sub_count`](Self::sub_count), this treats the map as a
+    /// multiset, where each key appears as many times as its count.
+    pub fn add_count(&mut self, key: K, delta: V)
+    where
+        V: AddAssign,
+    {
+        match self.search(&key) {
+            Ok(i) => self.inner[i].1 += delta,
+            Err(i) => {
+                self.insert_at(i, (key, delta));
+                self.debug_assert_invariants();
+            }
+        }
+    }
+
+    /// Subtracts `delta` from the count at the given key, removing the key entirely if its
+    /// count reaches zero - or would go below zero, so unsigned counts saturate rather than
+    /// underflow. Does nothing if the key isn't present.
+    pub fn sub_count(&mut self, key: &K, delta: V)
+    where
+        V: PartialOrd + SubAssign,
+    {
+        if let Ok(i) = self.search(key) {
+            if self.inner[i].1 <= delta {
+                self.inner.remove(i);
+            } else {
+                self.inner[i].1 -= delta;
+            }
+        }
+    }
+
+    /// Like [`insert`](Self::insert), but refuses to insert a new key if the map
+    /// would then have more than `max_len` entries, returning the rejected pair instead.
+    /// Updating the value of an existing key always succeeds, regardless of `max_len`.
+    pub fn insert_within(&mut self, key: K, val: V, max_len: usize) -> Result<Option<V>, (K, V)> {
+        match self.search(&key) {
+            Ok(i) => Ok(Some(std::mem::replace(&mut self.inner[i].1, val))),
+            Err(_) if self.len() >= max_len => Err((key, val)),
+            Err(i) => {
+                self.insert_at(i, (key, val));
+                self.debug_assert_invariants();
+                Ok(None)
+            }
+        }
+    }
+
+    /// Tries to insert a key-value pair into the map, and returns a mutable
+    /// reference to the value in the entry.
+    ///
+    /// If the map already had this key present, nothing is updated, and an error
+    /// containing the existing value and the value which wasn't inserted is returned.
+    pub fn try_insert(&mut self, key: K, val: V) -> Result<&mut V, OccupiedError<'_, K, V>> {
+        match self.search(&key) {
+            Ok(i) => {
+                let (key, existing) = &mut self.inner[i];
+                Err(OccupiedError {
+                    key,
+                    existing,
+                    value: val,
+                })
+            }
+            Err(i) => {
+                self.insert_at(i, (key, val));
+                self.debug_assert_invariants();
+                Ok(&mut self.inner[i].1)
+            }
+        }
+    }
+
+    /// Returns a mutable reference to the value at the given key, inserting `default`
+    /// first if the key isn't present. The flag is `true` if `default` was inserted, and
+    /// `false` if the key was already present - telling apart e.g. a newly seen prime
+    /// factor from a repeated one, without a separate lookup.
+    pub fn insert_or_get(&mut self, key: K, default: V) -> (&mut V, bool) {
+        match self.search(&key) {
+            Ok(i) => (&mut self.inner[i].1, false),
+            Err(i) => {
+                self.insert_at(i, (key, default));
+                self.debug_assert_invariants();
+                (&mut self.inner[i].1, true)
+            }
+        }
+    }
+
+    /// Returns a [`TinyMapHandle`] to the entry at the given key, if it exists,
+    /// which allows repeated access to its value without searching again.
+    pub fn handle(&self, key: &K) -> Option<TinyMapHandle<K>>
+    where
+        K: Clone,
+    {
+        self.search(key).ok().map(|idx| TinyMapHandle {
+            key: key.clone(),
+            idx,
+        })
+    }
+
+    /// The value of the entry referred to by `handle`, or `None` if the handle
+    /// has been invalidated (see [`TinyMapHandle`]).
+    pub fn get_by_handle(&self, handle: &TinyMapHandle<K>) -> Option<&V> {
+        self.inner
+            .get(handle.idx)
+            .filter(|(key, _)| *key == handle.key)
+            .map(|(_, v)| v)
+    }
+
+    /// Mutable access to the value of the entry referred to by `handle`, or `None`
+    /// if the handle has been invalidated (see [`TinyMapHandle`]).
+    pub fn get_mut_by_handle(&mut self, handle: &TinyMapHandle<K>) -> Option<&mut V> {
+        self.inner
+            .get_mut(handle.idx)
+            .filter(|(key, _)| *key == handle.key)
+            .map(|(_, v)| v)
+    }
+
+    /// An iterator over the keys common to both maps, along with their values
+    /// in `self` and `other` respectively, in sorted order.
+    ///
+    /// Since both maps are sorted, this is a linear merge - `O(n + m)`.
+    pub fn intersection<'a>(
+        &'a self,
+        other: &'a Self,
+    ) -> impl Iterator<Item = (&'a K, &'a V, &'a V)> {
+        let (mut i, mut j) = (0, 0);
+        std::iter::from_fn(move || {
+            while let (Some((k1, v1)), Some((k2, v2))) = (self.inner.get(i), other.inner.get(j)) {
+                match k1.cmp(k2) {
+                    Ordering::Less => i += 1,
+                    Ordering::Greater => j += 1,
+                    Ordering::Equal => {
+                        i += 1;
+                        j += 1;
+                        return Some((k1, v1, v2));
+                    }
+                }
+            }
+            None
+        })
+    }
+
+    /// An iterator over the entries of `self` whose keys aren't in `other`, in sorted
+    /// order. For factor multisets, these are the primes dividing one number but not
+    /// the other.
+    ///
+    /// Since both maps are sorted, this is a linear merge - `O(n + m)`.
+    pub fn difference<'a>(&'a self, other: &'a Self) -> impl Iterator<Item = (&'a K, &'a V)> {
+        let (mut i, mut j) = (0, 0);
+        std::iter::from_fn(move || {
+            while let Some((k1, v1)) = self.inner.get(i) {
+                match other.inner.get(j).map(|(k2, _)| k1.cmp(k2)) {
+                    Some(Ordering::Greater) => j += 1,
+                    Some(Ordering::Equal) => {
+                        i += 1;
+                        j += 1;
+                    }
+                    // Nothing left in `other` could match this key
+                    Some(Ordering::Less) | None => {
+                        i += 1;
+                        return Some((k1, v1));
+                    }
+                }
+            }
+            None
+        })
+    }
+
+    /// Whether or not every key in `self` is also a key in `other`.
+    ///
+    /// Since both maps are sorted, this is a linear merge - `O(n + m)`.
+    pub fn is_key_subset(&self, other: &Self) -> bool {
+        let mut other_keys = other.keys();
+        self.keys()
+            .all(|key| other_keys.by_ref().find(|other_key| *other_key >= key) == Some(key))
+    }
+
+    /// Adds one to the value at each of `keys`, inserting a value of one for keys which
+    /// aren't present, as [`increment`](Self::increment) does for a single key. `keys`
+    /// must be in increasing order, but may repeat.
+    ///
+    /// Rather than searching for each key, this merges the keys into the map in a single
+    /// linear pass - `O(n + m)`, like [`merge_sorted`](Self::merge_sorted).
+    pub fn bulk_increment_sorted(&mut self, keys: impl IntoIterator<Item = K>)
+    where
+        V: From<u8> + AddAssign,
+    {
+        let mut keys = keys.into_iter().peekable();
+        let old = std::mem::take(&mut self.inner);
+        let mut inner = TinyVec::with_capacity(old.len());
+        let mut old = old.into_iter().peekable();
+
+        loop {
+            let next = match (old.peek(), keys.peek()) {
+                (Some((k, _)), Some(key)) if k > key => keys.next().map(|key| (key, V::from(1))),
+                (None, Some(_)) => keys.next().map(|key| (key, V::from(1))),
+                _ => old.next(),
+            };
+            let Some((key, mut val)) = next else {
+                break;
+            };
+
+            // Take the whole run of this key at once
+            while keys.next_if_eq(&key).is_some() {
+                val += V::from(1);
+            }
+            debug_assert!(
+                keys.peek().is_none_or(|next| *next > key),
+                "keys passed to bulk_increment_sorted are not sorted"
+            );
+
+            P::make_room(&mut inner);
+            inner.push((key, val));
+        }
+
+        self.inner = inner;
+        self.debug_assert_invariants();
+    }
+
+    /// Merges two maps into one, applying `combine` to the values of keys which are
+    /// present in both. For factor multisets, `merge_sorted(a, b, |j, k| j + k)` is the
+    /// factorization of the product of the two numbers.
+    ///
+    /// Since both maps are sorted, this is a linear merge - `O(n + m)`.
+    pub fn merge_sorted(a: Self, b: Self, mut combine: impl FnMut(V, V) -> V) -> Self {
+        let mut inner = TinyVec::with_capacity(a.len().max(b.len()));
+        let mut a = a.inner.into_iter().peekable();
+        let mut b = b.inner.into_iter().peekable();
+
+        loop {
+            let next = match (a.peek(), b.peek()) {
+                (Some((ka, _)), Some((kb, _))) => match ka.cmp(kb) {
+                    Ordering::Less => a.next(),
+                    Ordering::Greater => b.next(),
+                    Ordering::Equal => a
+                        .next()
+                        .zip(b.next())
+                        .map(|((k, va), (_, vb))| (k, combine(va, vb))),
+                },
+                (Some(_), None) => a.next(),
+                (None, _) => b.next(),
+            };
+
+            match next {
+                Some(entry) => {
+                    P::make_room(&mut inner);
+                    inner.push(entry);
+                }
+                None => break,
+            }
+        }
+
+        let merged = Self {
+            inner,
+            policy: PhantomData,
+        };
+        merged.debug_assert_invariants();
+        merged
+    }
+
+    /// Finds the index of `key` in the backing storage, or the index where it would
+    /// be inserted if it isn't present - like [`slice::binary_search`].
+    fn search(&self, key: &K) -> Result<usize, usize> {
+        if self.len() <= LINEAR_SEARCH_MAX_LEN {
+            // Counting the smaller keys without branching, rather than stopping early,
+            // is what makes this fast
+            let idx = self.inner.iter().map(|(k, _)| usize::from(k < key)).sum();
+            match self.inner.get(idx) {
+                Some((k, _)) if k == key => Ok(idx),
+                _ => Err(idx),
+            }
+        } else {
+            self.inner.binary_search_by_key(&key, |(key, _)| key)
+        }
+    }
+
+    /// Whether the keys are strictly increasing, and the length is within the capacity -
+    /// the invariants every method keeps. For fuzzing and property-based tests, which can
+    /// check this after each operation.
+    #[cfg(feature = "testing")]
+    pub fn check_invariants(&self) -> bool {
+        self.check_invariants_impl()
+    }
+
+    /// Checks that the keys are strictly increasing, and that the length is within
+    /// the capacity. Should be called at the end of any method which changes the
+    /// structure of the map. Does nothing in release builds.
+    fn debug_assert_invariants(&self) {
+        debug_assert!(
+            self.check_invariants_impl(),
+            "TinyMap keys are not strictly increasing, or its length exceeds its capacity"
+        );
+    }
+
+    /// The invariants behind both [`check_invariants`](Self::check_invariants) and
+    /// [`debug_assert_invariants`](Self::debug_assert_invariants).
+    fn check_invariants_impl(&self) -> bool {
+        self.inner.windows(2).all(|w| w[0].0 < w[1].0) && self.len() <= self.capacity()
+    }
 }
 
-impl<K: Default, V: Default, const N: usize> Extend<(K, V)> for TinyMap<K, V, N> {
-    delegate! {
-        to self.inner {
-            fn extend<T: IntoIterator<Item = (K, V)>>(&mut self, iter: T);
+impl<K: Default + Debug, V: Default + Debug, const N: usize, P: CapacityPolicy> Debug
+    for TinyMap<K, V, N, P>
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K: Default + Ord, V: Default, const N: usize, P: CapacityPolicy> From<BTreeMap<K, V>>
+    for TinyMap<K, V, N, P>
+{
+    fn from(map: BTreeMap<K, V>) -> Self {
+        // BTreeMap iterates in sorted order, so there's no need to search for
+        // where each element goes
+        Self {
+            inner: map.into_iter().collect(),
+            policy: PhantomData,
         }
     }
 }
 
+impl<K: Default + Ord, V: Default, const N: usize, P: CapacityPolicy> From<TinyMap<K, V, N, P>>
+    for BTreeMap<K, V>
+{
+    fn from(map: TinyMap<K, V, N, P>) -> Self {
+        map.inner.into_iter().collect()
+    }
+}
+
+impl<K: Default + PartialEq, V: Default + PartialEq, const N: usize, P: CapacityPolicy>
+    PartialEq<BTreeMap<K, V>> for TinyMap<K, V, N, P>
+{
+    fn eq(&self, other: &BTreeMap<K, V>) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl<K: Default + Ord, V: Default, const N: usize, P: CapacityPolicy> Extend<(K, V)>
+    for TinyMap<K, V, N, P>
+{
+    /// Inserts every key-value pair from the iterator, in order - so for duplicate
+    /// keys, the last value wins.
+    fn extend<T: IntoIterator<Item = (K, V)>>(&mut self, iter: T) {
+        for (key, val) in iter {
+            self.insert(key, val);
+        }
+        self.debug_assert_invariants();
+    }
+}
+
+/// How the backing storage of a [`TinyMap`] grows when an element is inserted into a
+/// map which is already at capacity - including when the map first spills out of its
+/// inline storage onto the heap. Chosen by the last type parameter of [`TinyMap`].
+pub trait CapacityPolicy {
+    /// Called before every insertion into `storage` - makes room for one more element, if
+    /// needed. Anything not reserved here is left to [`TinyVec`]'s own (doubling) growth.
+    fn make_room<A: Array>(storage: &mut TinyVec<A>);
+}
+
+/// The default [`CapacityPolicy`]: the same amortized doubling growth as [`Vec`]. A map
+/// spilling out of `N` inline elements moves to a heap buffer with room for `2N`, and
+/// the buffer doubles from there. Inserts are amortized `O(1)` (ignoring the shifting of
+/// later elements), at the cost of up to about half of the heap buffer going unused.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Doubling;
+
+impl CapacityPolicy for Doubling {
+    fn make_room<A: Array>(_storage: &mut TinyVec<A>) {}
+}
+
+/// A [`CapacityPolicy`] which grows the backing storage by exactly one element at a time,
+/// so that a map on the heap never has any unused capacity. Every insertion into a map
+/// on the heap reallocates, and potentially copies the whole map - so this is only a good
+/// choice for maps which are expected to spill just past `N` elements and no further,
+/// such as a [`FactorMultiset`](factor::FactorMultiset): a number with 4 prime factors takes a
+/// heap buffer of 4 entries, rather than 6 entries under [`Doubling`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Exact;
+
+impl CapacityPolicy for Exact {
+    fn make_room<A: Array>(storage: &mut TinyVec<A>) {
+        if storage.len() == storage.capacity() {
+            storage.reserve_exact(1);
+        }
+    }
+}
+
+/// A pool of heap buffers from [`TinyMap`]s which have spilled out of their inline
+/// storage, for reuse by new maps. Reusing buffers saves a trip to the allocator
+/// when many maps are created and discarded. See [`TinyMap::recycle_into`] and
+/// [`TinyMap::new_in`].
+#[derive(Debug)]
+pub struct TinyMapPool<K, V> {
+    buffers: Vec<Vec<(K, V)>>,
+}
+
+impl<K, V> TinyMapPool<K, V> {
+    /// Creates a new empty pool.
+    pub fn new() -> Self {
+        Self {
+            buffers: Vec::new(),
+        }
+    }
+
+    /// The number of buffers available for reuse.
+    pub fn len(&self) -> usize {
+        self.buffers.len()
+    }
+
+    /// Whether or not there are no buffers available for reuse.
+    pub fn is_empty(&self) -> bool {
+        self.buffers.is_empty()
+    }
+}
+
+impl<K, V> Default for TinyMapPool<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A handle to an entry of a [`TinyMap`], remembering where in the backing storage
+/// the entry lives, so that its value can be accessed repeatedly without searching
+/// for its key each time. Created by [`TinyMap::handle`].
+///
+/// A handle is invalidated when its entry moves within the backing storage - that is,
+/// when its entry is removed, or when any entry with a smaller key is inserted or removed.
+/// Modifying values, or inserting/removing entries with larger keys, does not invalidate it.
+/// Using an invalidated handle is not unsafe: the handle's key is checked on every access,
+/// and accesses through an invalidated handle return `None`.
+#[derive(Clone, Debug)]
+pub struct TinyMapHandle<K> {
+    key: K,
+    idx: usize,
+}
+
 /// A symbolic "entry" into a [`TinyMap`] at a specific key. Enables
 /// in-place modification and delayed insertion of new values at that key.
-pub enum TinyMapEntry<'a, K: Default, V: Default, const N: usize> {
-    /// If the key already exists in the map, this is a pointer to its place in the
-    /// backing storage.
-    Occupied(&'a mut (K, V)),
-    /// Otherwise, keep track of where in the backing storage we should insert
-    /// a new element, should we want to.
-    Vacant {
-        inner: &'a mut TinyVec<[(K, V); N]>,
-        key: K,
-        idx: usize,
-    },
+pub enum TinyMapEntry<'a, K: Default, V: Default, const N: usize, P: CapacityPolicy = Doubling> {
+    /// The key already exists in the map.
+    Occupied(OccupiedEntry<'a, K, V, N, P>),
+    /// The key doesn't exist in the map yet.
+    Vacant(VacantEntry<'a, K, V, N, P>),
 }
 
-impl<'a, K: Default, V: Default, const N: usize> TinyMapEntry<'a, K, V, N> {
+impl<'a, K: Default, V: Default, const N: usize, P: CapacityPolicy> TinyMapEntry<'a, K, V, N, P> {
     /// Provides in-place mutable access to an occupied entry before any potential inserts into the map.
     pub fn and_modify(mut self, f: impl FnOnce(&mut V)) -> Self {
         if let Self::Occupied(entry) = &mut self {
-            f(&mut entry.1);
+            f(entry.get_mut());
+        }
+        self
+    }
+
+    /// Like [`and_modify`](Self::and_modify), but replaces the value of an occupied entry
+    /// with `f` of the old value, for new values which can't be computed in place. The
+    /// old value is moved out with [`std::mem::take`], leaving a default in its place
+    /// until `f` returns.
+    pub fn and_replace(mut self, f: impl FnOnce(V) -> V) -> Self {
+        if let Self::Occupied(entry) = &mut self {
+            let val = entry.get_mut();
+            *val = f(std::mem::take(val));
         }
         self
     }
 
+    /// Removes an occupied entry from the map if its value satisfies `pred`, leaving
+    /// a vacant entry in its place. Useful after [`and_modify`](Self::and_modify), e.g.
+    /// for removing a count which has been decremented to zero.
+    pub fn and_remove_if(self, pred: impl FnOnce(&V) -> bool) -> Self {
+        match self {
+            TinyMapEntry::Occupied(OccupiedEntry { map, idx }) if pred(&map.inner[idx].1) => {
+                let (key, _) = map.inner.remove(idx);
+                TinyMapEntry::Vacant(VacantEntry { map, key, idx })
+            }
+            entry => entry,
+        }
+    }
+
     /// Ensures a value is in the entry by inserting the default if empty, and returns a mutable reference to the value in the entry.
     pub fn or_insert(self, default: V) -> &'a mut V {
         match self {
-            TinyMapEntry::Occupied(entry) => &mut entry.1,
-            TinyMapEntry::Vacant { inner, key, idx } => {
-                inner.insert(idx, (key, default));
-                &mut inner[idx].1
+            TinyMapEntry::Occupied(entry) => entry.into_mut(),
+            TinyMapEntry::Vacant(entry) => entry.insert(default),
+        }
+    }
+
+    /// Like [`or_insert`](Self::or_insert), but returns a reference to the key
+    /// in the entry as well.
+    pub fn or_insert_entry(self, default: V) -> (&'a K, &'a mut V) {
+        let (map, idx) = match self {
+            TinyMapEntry::Occupied(OccupiedEntry { map, idx }) => (map, idx),
+            TinyMapEntry::Vacant(VacantEntry { map, key, idx }) => {
+                map.insert_at(idx, (key, default));
+                (map, idx)
             }
+        };
+        let (key, val) = &mut map.inner[idx];
+        (key, val)
+    }
+}
+
+/// An entry of a [`TinyMap`] whose key already exists, keeping track of its
+/// place in the backing storage. Part of a [`TinyMapEntry`].
+pub struct OccupiedEntry<'a, K: Default, V: Default, const N: usize, P: CapacityPolicy = Doubling> {
+    map: &'a mut TinyMap<K, V, N, P>,
+    idx: usize,
+}
+
+impl<'a, K: Default, V: Default, const N: usize, P: CapacityPolicy> OccupiedEntry<'a, K, V, N, P> {
+    /// The key of the entry.
+    pub fn key(&self) -> &K {
+        &self.map.inner[self.idx].0
+    }
+
+    /// The value of the entry.
+    pub fn get(&self) -> &V {
+        &self.map.inner[self.idx].1
+    }
+
+    /// A mutable reference to the value of the entry. See [`into_mut`](Self::into_mut)
+    /// for a reference which outlives the entry.
+    pub fn get_mut(&mut self) -> &mut V {
+        &mut self.map.inner[self.idx].1
+    }
+
+    /// Converts the entry into a mutable reference to its value, with the lifetime
+    /// of the map.
+    pub fn into_mut(self) -> &'a mut V {
+        &mut self.map.inner[self.idx].1
+    }
+}
+
+/// An entry of a [`TinyMap`] whose key doesn't exist yet, keeping track of where in
+/// the backing storage a new element should be inserted, should we want to. Part of
+/// a [`TinyMapEntry`].
+pub struct VacantEntry<'a, K: Default, V: Default, const N: usize, P: CapacityPolicy = Doubling> {
+    map: &'a mut TinyMap<K, V, N, P>,
+    key: K,
+    idx: usize,
+}
+
+impl<'a, K: Default, V: Default, const N: usize, P: CapacityPolicy> VacantEntry<'a, K, V, N, P> {
+    /// The key which would be inserted.
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// Inserts `value` at the entry's key, and returns a mutable reference to it. Unlike
+    /// [`TinyMapEntry::or_insert`], there is no need to check whether the key exists.
+    pub fn insert(self, value: V) -> &'a mut V {
+        self.map.insert_at(self.idx, (self.key, value));
+        &mut self.map.inner[self.idx].1
+    }
+}
+
+/// The error returned by [`TinyMap::try_insert`] when the key already exists.
+#[derive(Debug)]
+pub struct OccupiedError<'a, K, V> {
+    /// The key which was already present in the map.
+    pub key: &'a K,
+    /// The value already present in the map at that key.
+    pub existing: &'a mut V,
+    /// The value which was not inserted.
+    pub value: V,
+}
+
+impl<K: Debug, V: Debug> std::fmt::Display for OccupiedError<'_, K, V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "failed to insert {:?}, key {:?} already exists with value {:?}",
+            self.value, self.key, self.existing,
+        )
+    }
+}
+
+impl<K: Debug, V: Debug> std::error::Error for OccupiedError<'_, K, V> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::factor::{FactorMultiset, factorize};
+
+    type Map = TinyMap<u32, u32, 4>;
+
+    /// A map with the given entries, which may be in any order.
+    fn map(entries: &[(u32, u32)]) -> Map {
+        let mut map = Map::new();
+        map.extend(entries.iter().copied());
+        map
+    }
+
+    #[test]
+    fn intersection_yields_common_keys_with_both_values() {
+        let a = map(&[(2, 1), (3, 2), (5, 1), (11, 4)]);
+        let b = map(&[(3, 7), (7, 1), (11, 2), (13, 1)]);
+
+        assert_eq!(
+            a.intersection(&b).collect::<Vec<_>>(),
+            [(&3, &2, &7), (&11, &4, &2)]
+        );
+        assert_eq!(
+            b.intersection(&a).collect::<Vec<_>>(),
+            [(&3, &7, &2), (&11, &2, &4)]
+        );
+        assert_eq!(a.intersection(&Map::new()).count(), 0);
+    }
+
+    #[test]
+    fn shrink_to_respects_the_minimum_capacity() {
+        let mut m = map(&(0..20).map(|k| (k, k)).collect::<Vec<_>>());
+        m.retain(|&k, _| k < 6);
+        m.shrink_to(10);
+        assert!(!m.is_inline());
+        assert!(
+            (10..20).contains(&m.capacity()),
+            "capacity {}",
+            m.capacity()
+        );
+
+        // Never below the length
+        m.shrink_to(5);
+        assert_eq!(m.capacity(), 6);
+
+        // A minimum which fits inline shrinks all the way, moving inline if it can
+        m.retain(|&k, _| k < 3);
+        m.shrink_to(2);
+        assert!(m.is_inline());
+        assert_eq!(m.capacity(), 4);
+
+        // Inline maps are left inline, even for a minimum past N
+        m.shrink_to(10);
+        assert!(m.is_inline());
+    }
+
+    #[test]
+    fn btree_map_conversions_round_trip() {
+        // Both inline and spilled onto the heap
+        for len in [3, 10] {
+            let btree: BTreeMap<u32, u32> = (0..len).rev().map(|k| (k * 7 % 11, k)).collect();
+            let m = Map::from(btree.clone());
+            assert!(m.check_invariants_impl());
+            assert_eq!(m, btree);
+            assert_eq!(BTreeMap::from(m), btree);
         }
+
+        let m = map(&[(5, 1), (2, 3)]);
+        assert_ne!(m, BTreeMap::from([(2, 3)]));
+        assert_ne!(m, BTreeMap::from([(2, 3), (5, 2)]));
+    }
+
+    #[test]
+    fn is_key_subset_compares_key_sets() {
+        let a = map(&[(3, 1), (7, 9)]);
+        let b = map(&[(2, 1), (3, 2), (5, 1), (7, 1)]);
+
+        // Values don't matter, only keys
+        assert!(a.is_key_subset(&b));
+        assert!(!b.is_key_subset(&a));
+        assert!(b.is_key_subset(&b));
+        assert!(!a.is_key_subset(&map(&[(2, 1), (5, 1)])));
+        assert!(!a.is_key_subset(&map(&[(3, 1), (5, 1)])));
+        assert!(Map::new().is_key_subset(&a));
+        assert!(!a.is_key_subset(&Map::new()));
+    }
+
+    #[test]
+    fn reserve_exact_stays_inline_or_allocates_exactly() {
+        let mut m = map(&[(1, 1), (2, 2), (3, 3)]);
+        m.reserve_exact(1);
+        assert!(m.is_inline());
+        assert_eq!(m.capacity(), 4);
+
+        m.reserve_exact(3);
+        assert!(!m.is_inline());
+        assert_eq!(m.capacity(), 6);
+        assert_eq!(m.to_vec(), [(1, 1), (2, 2), (3, 3)]);
+    }
+
+    #[test]
+    fn retain_count_matches_length_change() {
+        let mut m = map(&(1..=10).map(|k| (k, k * k)).collect::<Vec<_>>());
+        let removed = m.retain_count(|&k, &v| k % 3 != 0 && v < 50);
+        assert_eq!(removed, 10 - m.len());
+        assert_eq!(m.to_vec(), [(1, 1), (2, 4), (4, 16), (5, 25), (7, 49)]);
+        assert_eq!(m.retain_count(|_, _| true), 0);
+    }
+
+    #[test]
+    fn merge_sorted_multiplies_factorizations() {
+        let product = |a: usize, b: usize| {
+            TinyMap::merge_sorted(factorize(a), factorize(b), |j, k| j + k).to_vec()
+        };
+        // Overlapping primes
+        assert_eq!(product(12, 90), factorize(1080).to_vec());
+        // Disjoint primes, interleaved and not
+        assert_eq!(product(10, 21), factorize(210).to_vec());
+        assert_eq!(product(8, 15), factorize(120).to_vec());
+        // One side empty
+        assert_eq!(product(1, 49), factorize(49).to_vec());
+
+        let merged = TinyMap::merge_sorted(
+            map(&[(1, 1), (3, 3), (5, 5)]),
+            map(&[(2, 2), (3, 30), (6, 6)]),
+            |a, b| a.max(b),
+        );
+        assert_eq!(merged.to_vec(), [(1, 1), (2, 2), (3, 30), (5, 5), (6, 6)]);
+    }
+
+    #[test]
+    fn from_vec_sorts_and_keeps_last_duplicate() {
+        let m = Map::from_vec(vec![(5, 1), (2, 1), (5, 2), (9, 1), (2, 7), (5, 3)]);
+        assert_eq!(m.to_vec(), [(2, 7), (5, 3), (9, 1)]);
+        assert!(m.is_inline());
+
+        // Too many keys to fit inline
+        let m = Map::from_vec((0..10).rev().map(|k| (k % 7, k)).collect());
+        assert_eq!(
+            m.to_vec(),
+            [(0, 0), (1, 1), (2, 2), (3, 3), (4, 4), (5, 5), (6, 6)]
+        );
+        assert!(!m.is_inline());
+        assert!(m.check_invariants_impl());
+    }
+
+    #[test]
+    fn vec_conversions_round_trip() {
+        for len in [0, 3, 10] {
+            let entries: Vec<_> = (0..len).map(|k| (k * 2, k)).collect();
+            let m = Map::from_vec(entries.clone());
+            assert_eq!(m.to_vec(), entries);
+            assert_eq!(m.into_vec(), entries);
+        }
+    }
+
+    #[test]
+    fn last_key_matching_searches_from_the_back() {
+        let m = factorize(2 * 3 * 5 * 7 * 7 * 11 * 13);
+        assert_eq!(m.last_key_matching(|&p| p < 10), Some((&7, &2)));
+        assert_eq!(m.last_key_matching(|&p| p < 3), Some((&2, &1)));
+        assert_eq!(m.last_key_matching(|&p| p > 100), None);
+        assert_eq!(m.last_key_matching(|_| true), m.iter().next_back());
+        assert_eq!(
+            m.keys().rev().copied().collect::<Vec<_>>(),
+            [13, 11, 7, 5, 3, 2]
+        );
+    }
+
+    #[test]
+    fn insert_or_get_reports_whether_it_inserted() {
+        let mut m = map(&[(3, 1)]);
+
+        let (v, inserted) = m.insert_or_get(5, 1);
+        assert!(inserted);
+        *v += 10;
+
+        let (v, inserted) = m.insert_or_get(3, 100);
+        assert!(!inserted);
+        assert_eq!(*v, 1);
+        *v += 1;
+
+        assert_eq!(m.to_vec(), [(3, 2), (5, 11)]);
+    }
+
+    #[test]
+    fn product_values_gives_number_of_divisors() {
+        for n in 1..500 {
+            let factors = factorize(n);
+            let d = factors.product_values(|&k| u64::from(k) + 1);
+            assert_eq!(d, crate::oracle::num_factors(n), "d({n})");
+        }
+
+        // Ω(n), the number of prime factors counted with multiplicity
+        assert_eq!(factorize(360).sum_values(|&k| u32::from(k)), 6);
+        assert_eq!(factorize(1).sum_values(|&k| u32::from(k)), 0);
+        assert_eq!(factorize(1).product_values(|&k| u64::from(k) + 1), 1);
+    }
+
+    #[test]
+    fn partition_splits_into_two_valid_maps() {
+        let m = factorize(8 * 3 * 25 * 7 * 11 * 13);
+        let (small, large) = m.clone().partition(|&p| p < 6);
+
+        assert_eq!(small.to_vec(), [(2, 3), (3, 1), (5, 2)]);
+        assert_eq!(large.to_vec(), [(7, 1), (11, 1), (13, 1)]);
+        assert!(small.check_invariants_impl() && large.check_invariants_impl());
+
+        // Together, the halves are exactly the original
+        assert!(small.intersection(&large).next().is_none());
+        assert_eq!(
+            TinyMap::merge_sorted(small, large, |_, _| unreachable!()).to_vec(),
+            m.to_vec()
+        );
+
+        let (all, none) = m.clone().partition(|_| true);
+        assert_eq!(all.to_vec(), m.to_vec());
+        assert!(none.is_empty());
+    }
+
+    #[test]
+    fn add_and_sub_count_remove_at_zero() {
+        let mut m = Map::new();
+        m.add_count(3, 2);
+        m.add_count(5, 1);
+        m.add_count(3, 4);
+        assert_eq!(m.to_vec(), [(3, 6), (5, 1)]);
+
+        m.sub_count(&3, 2);
+        assert_eq!(m.get(&3), Some(&4));
+
+        // Reaching zero removes the key
+        m.sub_count(&5, 1);
+        assert_eq!(m.get(&5), None);
+
+        // So does going below zero, rather than underflowing
+        m.sub_count(&3, 10);
+        assert!(m.is_empty());
+
+        // Absent keys are left alone
+        m.sub_count(&7, 1);
+        assert!(m.is_empty());
+    }
+
+    #[test]
+    fn difference_yields_keys_missing_from_other() {
+        let diff = |a: &Map, b: &Map| a.difference(b).map(|(&k, &v)| (k, v)).collect::<Vec<_>>();
+        let a = map(&[(2, 1), (3, 2), (5, 1), (11, 4)]);
+
+        // Disjoint
+        assert_eq!(diff(&a, &map(&[(7, 1), (13, 1)])), a.to_vec());
+        // Overlapping
+        assert_eq!(
+            diff(&a, &map(&[(1, 1), (3, 7), (11, 2), (13, 1)])),
+            [(2, 1), (5, 1)]
+        );
+        // Identical keys, even with different values
+        assert_eq!(diff(&a, &map(&[(2, 9), (3, 9), (5, 9), (11, 9)])), []);
+        assert_eq!(diff(&a, &Map::new()), a.to_vec());
+        assert_eq!(diff(&Map::new(), &a), []);
+    }
+
+    #[test]
+    fn reserve_and_with_capacity_spill_only_past_n() {
+        let mut m = map(&[(1, 1)]);
+        m.reserve(3);
+        assert!(m.is_inline());
+        assert_eq!(m.capacity(), 4);
+        m.reserve(4);
+        assert!(!m.is_inline());
+        assert!(m.capacity() >= 5);
+
+        assert!(Map::with_capacity(0).is_inline());
+        assert!(Map::with_capacity(4).is_inline());
+        let m = Map::with_capacity(5);
+        assert!(!m.is_inline());
+        assert!(m.capacity() >= 5);
+    }
+
+    #[test]
+    fn from_iter_summing_sums_duplicate_keys() {
+        let m = FactorMultiset::from_iter_summing([(3, 1), (2, 1), (5, 1), (2, 1), (2, 1), (3, 1)]);
+        assert_eq!(m.to_vec(), factorize(360).to_vec());
+
+        let m = Map::from_iter_summing([(7, 5), (1, 2), (7, 10), (4, 0)]);
+        assert_eq!(m.to_vec(), [(1, 2), (4, 0), (7, 15)]);
+        assert!(Map::from_iter_summing([]).is_empty());
+    }
+
+    #[test]
+    fn position_of_is_the_rank_of_a_key() {
+        let m = factorize(2 * 3 * 5 * 7 * 11 * 11);
+        assert_eq!(
+            [1, 2, 3, 5, 7, 11, 13].map(|p| m.position_of(&p)),
+            [None, Some(0), Some(1), Some(2), Some(3), Some(4), None]
+        );
+        for p in m.keys() {
+            assert_eq!(m.key_at(m.position_of(p).unwrap()), Some(p));
+        }
+        assert_eq!(FactorMultiset::new().position_of(&2), None);
+    }
+
+    #[test]
+    fn bulk_increment_sorted_counts_runs_of_keys() {
+        let mut m = FactorMultiset::new();
+        m.bulk_increment_sorted([2, 2, 2, 3, 5, 5, 7, 7]);
+        assert_eq!(m.to_vec(), [(2, 3), (3, 1), (5, 2), (7, 2)]);
+
+        // Merging into existing keys, before, between and after them
+        m.bulk_increment_sorted([1, 2, 4, 7, 7, 11]);
+        assert_eq!(
+            m.to_vec(),
+            [(1, 1), (2, 4), (3, 1), (4, 1), (5, 2), (7, 4), (11, 1)]
+        );
+        assert!(m.check_invariants_impl());
+
+        let mut m = factorize(12);
+        m.bulk_increment_sorted([]);
+        assert_eq!(m.to_vec(), [(2, 2), (3, 1)]);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "not sorted")]
+    fn bulk_increment_sorted_rejects_unsorted_keys() {
+        FactorMultiset::new().bulk_increment_sorted([3, 2]);
+    }
+
+    #[test]
+    fn retain_filter_map_updates_and_prunes_in_order() {
+        // Dividing by the radical decrements every power, dropping those which reach zero
+        let mut m = factorize(8 * 3 * 25 * 7 * 1331);
+        m.retain_filter_map(|_, k| k.checked_sub(1).filter(|&k| k > 0));
+        assert_eq!(m.to_vec(), [(2, 2), (5, 1), (11, 2)]);
+        assert!(m.check_invariants_impl());
+
+        let mut m = map(&(1..=8).map(|k| (k, k)).collect::<Vec<_>>());
+        m.retain_filter_map(|&k, v| (k % 3 != 0).then_some(v * 10));
+        assert_eq!(
+            m.to_vec(),
+            [(1, 10), (2, 20), (4, 40), (5, 50), (7, 70), (8, 80)]
+        );
+        assert!(m.check_invariants_impl());
     }
 }
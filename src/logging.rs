@@ -0,0 +1,93 @@
+//! A minimal logger for --log, writing messages to stderr at levels filtered by the
+//! `RUST_LOG` environment variable, in the style of `env_logger`. Until [`init`] is
+//! called, nothing is logged, and [`warn`] falls back to a plain warning.
+
+use std::{
+    fmt::{self, Arguments},
+    sync::atomic::{AtomicBool, AtomicU8, Ordering},
+};
+
+/// How important a message is, from most to least.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Error = 1,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Level::Error => "ERROR",
+            Level::Warn => "WARN",
+            Level::Info => "INFO",
+            Level::Debug => "DEBUG",
+            Level::Trace => "TRACE",
+        })
+    }
+}
+
+/// The least important level which is logged, or 0 if logging is off.
+static MAX_LEVEL: AtomicU8 = AtomicU8::new(0);
+
+/// Whether or not [`init`] has been called.
+static INITIALIZED: AtomicBool = AtomicBool::new(false);
+
+/// Starts logging, at the level given by `RUST_LOG`: one of `off`, `error`, `warn`,
+/// `info` (the default), `debug` or `trace`. As with `env_logger`, it may be a comma
+/// separated list of directives, where only those without a target, or for `based_num`,
+/// apply here.
+pub fn init() {
+    let spec = std::env::var("RUST_LOG").unwrap_or_default();
+    let mut max_level = Some(Level::Info);
+    for directive in spec.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+        let level = match directive.split_once('=') {
+            Some(("based_num", level)) => level,
+            Some(_) => continue,
+            None => directive,
+        };
+        max_level = match level.to_ascii_lowercase().as_str() {
+            "off" => None,
+            "error" => Some(Level::Error),
+            "warn" => Some(Level::Warn),
+            "info" => Some(Level::Info),
+            "debug" => Some(Level::Debug),
+            "trace" => Some(Level::Trace),
+            _ => {
+                eprintln!("warning: ignoring unknown log level {level:?} in RUST_LOG");
+                continue;
+            }
+        };
+    }
+    MAX_LEVEL.store(max_level.map_or(0, |level| level as u8), Ordering::Relaxed);
+    INITIALIZED.store(true, Ordering::Relaxed);
+}
+
+/// Whether or not logging has been started.
+pub fn initialized() -> bool {
+    INITIALIZED.load(Ordering::Relaxed)
+}
+
+/// Whether or not messages at `level` are logged.
+pub fn enabled(level: Level) -> bool {
+    level as u8 <= MAX_LEVEL.load(Ordering::Relaxed)
+}
+
+/// Logs `message` at `level`, if that level is enabled.
+pub fn log(level: Level, message: Arguments) {
+    if enabled(level) {
+        eprintln!("[{level}] {message}");
+    }
+}
+
+/// Logs `message` as a warning, or prints it as a plain warning if logging hasn't
+/// been started, so that warnings are never lost.
+pub fn warn(message: Arguments) {
+    if initialized() {
+        log(Level::Warn, message);
+    } else {
+        eprintln!("warning: {message}");
+    }
+}
@@ -1,15 +1,46 @@
-use std::{error::Error, fmt::Write, path::PathBuf, time::Duration};
+use std::{
+    collections::{HashSet, VecDeque},
+    error::Error,
+    fmt::{Arguments, Write},
+    num::NonZeroUsize,
+    path::PathBuf,
+    sync::mpsc::{self, SyncSender},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
 
-use based_num::TinyMap;
-use clap::Parser;
+use based_num::{
+    factor::{
+        Abundancy, FIXTURE_NUMBERS, FactorMultiset, basedness, divisors, factorize, num_factors,
+        sum_of_divisors,
+    },
+    records::{
+        RecordKind, Records, WindowMinima, based_numbers, based_numbers_below, records_below,
+    },
+    sieve::{CloneSieve, Sieve, SpfSieve},
+};
+use clap::{Parser, Subcommand, ValueEnum};
 use csv::Writer;
-use indicatif::{ProgressBar, ProgressIterator, ProgressState, ProgressStyle};
+use indicatif::{ProgressBar, ProgressBarIter, ProgressIterator, ProgressState, ProgressStyle};
 use serde::Serialize;
 
+use crate::logging::Level;
+
+#[cfg(feature = "count-allocations")]
+mod counting_allocator;
+mod logging;
+mod png;
+#[cfg(feature = "signals")]
+mod signals;
+
 #[derive(Parser)]
+#[command(args_conflicts_with_subcommands = true)]
 /// Calculate basedness for all numbers from 1 to a certain maximum (see MAX_NUM),
 /// then output the sequence of based numbers until that maximum.
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     #[arg(default_value_t = 100_000_000)]
     /// The maximum number to check basedness of.
     max_num: u64,
@@ -18,9 +49,363 @@ struct Args {
     /// Output calculated number info for numbers considered.
     output_csv: Option<PathBuf>,
 
+    #[arg(long, requires = "output_csv")]
+    /// Write --output-csv from a separate thread as numbers are calculated, rather than
+    /// keeping every number's info until the end. Overlaps calculation with writing,
+    /// and uses less memory.
+    parallel_output: bool,
+
+    #[arg(
+        long,
+        value_delimiter = ',',
+        default_value = "number,num_factors,num_prime_factors,basedness"
+    )]
+    /// Which columns of number info to output, in order, separated by commas.
+    columns: Vec<Column>,
+
+    #[arg(long)]
+    /// Print what each of the --columns means, then exit.
+    help_metrics: bool,
+
+    #[arg(long)]
+    /// Write a JSON Schema describing a row of --output-csv with the selected --columns,
+    /// for tools which validate the output.
+    json_schema: Option<PathBuf>,
+
+    #[arg(long)]
+    /// Only output info for numbers with at least this basedness. Numbers are only
+    /// output if they pass every filter.
+    basedness_min: Option<u64>,
+
+    #[arg(long)]
+    /// Only output info for numbers with at most this basedness. Numbers are only
+    /// output if they pass every filter.
+    basedness_max: Option<u64>,
+
+    #[arg(long)]
+    /// Only output info for the numbers listed in this file, one per line. Numbers are
+    /// only output if they pass every filter.
+    only: Option<PathBuf>,
+
     #[arg(short, long)]
     /// Histogram of prime factor distribution
     prime_factor_csv: Option<PathBuf>,
+
+    #[arg(long)]
+    /// Draw the prime factor histogram as a bar chart PNG, with a bar for each number
+    /// of distinct prime factors from 1, left to right.
+    histogram_png: Option<PathBuf>,
+
+    #[arg(long)]
+    /// Histogram of the basedness of each number, bucketed by --basedness-buckets.
+    basedness_csv: Option<PathBuf>,
+
+    #[arg(long, default_value = "linear:1", value_parser = parse_bucketing)]
+    /// How to bucket --basedness-csv: linear:WIDTH for buckets of a fixed width,
+    /// or log2 or log10 for buckets between consecutive powers of 2 or 10.
+    basedness_buckets: Bucketing,
+
+    #[arg(long)]
+    /// Histogram of the largest prime factor of each number, bucketed by its base-2
+    /// logarithm (rounded down): bucket b counts the numbers whose largest prime factor
+    /// p has 2^b <= p < 2^(b + 1).
+    largest_prime_factor_csv: Option<PathBuf>,
+
+    #[arg(long)]
+    /// Output each prime, one per line, e.g. for --primes-in of a later run.
+    prime_csv: Option<PathBuf>,
+
+    #[arg(long)]
+    /// Output each prime along with the gap to the next prime.
+    prime_gap_csv: Option<PathBuf>,
+
+    #[arg(long)]
+    /// Output each based number after the first, along with how much its basedness
+    /// grew from the previous based number's, as a difference and as a ratio.
+    jumps_csv: Option<PathBuf>,
+
+    #[arg(long)]
+    /// Output the number of primes and the number of based numbers up to x, for every
+    /// x which is a multiple of --interval (and for MAX_NUM).
+    counts_csv: Option<PathBuf>,
+
+    #[arg(long, default_value_t = NonZeroUsize::new(1_000_000).unwrap())]
+    /// How far apart the rows of --counts-csv are.
+    interval: NonZeroUsize,
+
+    #[arg(long)]
+    /// Also find the perfect numbers (σ(n) = 2n), and the numbers whose abundancy
+    /// σ(n) / n is larger than that of all smaller numbers.
+    abundancy: bool,
+
+    #[arg(long)]
+    /// Also find the anti-based numbers: composite numbers which are less based than
+    /// the --anti-based-window composite numbers on either side of them. The composite
+    /// numbers within the window of either end of the run are never anti-based.
+    anti_based: bool,
+
+    #[arg(long, default_value_t = NonZeroUsize::new(10).unwrap(), requires = "anti_based")]
+    /// How many composite numbers on either side an --anti-based number must be less
+    /// based than.
+    anti_based_window: NonZeroUsize,
+
+    #[arg(long, value_name = "B")]
+    /// Also find the B-smooth numbers: numbers whose prime factors are all no larger
+    /// than B (including 1, which has none).
+    smooth: Option<usize>,
+
+    #[arg(long, requires = "smooth")]
+    /// Output the --smooth numbers, rather than printing them.
+    smooth_csv: Option<PathBuf>,
+
+    #[arg(long)]
+    /// Factorize each number on demand from a table of smallest prime factors,
+    /// rather than cloning and keeping the factorization of every number.
+    /// Uses much less memory.
+    no_clone: bool,
+
+    #[arg(long, conflicts_with = "no_clone")]
+    /// Start the sieve off knowing the primes listed in this file, one per line (such
+    /// as a --prime-csv from a previous run), so that they don't need to be found by
+    /// trial division. The list must be in increasing order, and must have every prime
+    /// up to its largest one, which is checked before starting. Primes past the end of
+    /// the list are found as usual. Not needed by --no-clone, which finds all of its
+    /// primes while building its table anyway.
+    primes_in: Option<PathBuf>,
+
+    #[arg(long)]
+    /// Output the prime factorization of each number, like 2^3*3*5^2. Numbers are
+    /// only output if they pass every filter.
+    factorization_csv: Option<PathBuf>,
+
+    #[arg(long)]
+    /// Output the list of divisors of each number no larger than --divisors-below.
+    divisors_csv: Option<PathBuf>,
+
+    #[arg(long, default_value_t = 10_000)]
+    /// Only output divisors for numbers no larger than this.
+    divisors_below: u64,
+
+    #[arg(long, default_value_t = 1_000)]
+    /// Skip outputting divisors for numbers with more than this many divisors.
+    max_divisors: u64,
+
+    #[arg(long)]
+    /// Print a checksum of the calculated number info, for checking that two runs
+    /// produced identical results.
+    checksum: bool,
+
+    #[arg(long, conflicts_with_all = [
+        "output_csv",
+        "prime_factor_csv",
+        "basedness_csv",
+        "largest_prime_factor_csv",
+        "prime_csv",
+        "prime_gap_csv",
+        "jumps_csv",
+        "counts_csv",
+        "smooth_csv",
+        "divisors_csv",
+        "factorization_csv",
+        "emit_records_live",
+        "tail",
+    ])]
+    /// Only print aggregate statistics (the number of based numbers and primes,
+    /// the largest basedness, and the prime factor histogram), without keeping
+    /// or outputting anything per number.
+    count_only: bool,
+
+    #[arg(long)]
+    /// Seed the based numbers from a CSV written by a previous run with --output-csv,
+    /// which must include the number and basedness columns for every number from 1.
+    /// Only the based records up to the last number in the CSV are reused - everything
+    /// else (the sieve, the histogram, the other records and all output) is still
+    /// calculated from scratch, starting from 2.
+    resume_from_csv: Option<PathBuf>,
+
+    #[arg(long)]
+    /// Stop as soon as a based number with at least this basedness is found, rather than
+    /// going all the way to MAX_NUM. Everything output only covers the numbers up to it.
+    stop_at_basedness: Option<u64>,
+
+    #[arg(long, value_parser = parse_duration)]
+    /// Stop once the run has taken this long, rather than going all the way to MAX_NUM.
+    /// Everything output only covers the numbers up to where it stopped. Accepts
+    /// suffixes s, m and h, e.g. 30s or 5m (seconds if there is no suffix).
+    time_limit: Option<Duration>,
+
+    #[cfg(feature = "signals")]
+    #[arg(long)]
+    /// On the first SIGINT or SIGTERM (e.g. Ctrl-C), stop and output everything
+    /// calculated so far, as if MAX_NUM had been reached. A second signal exits immediately.
+    dump_state_on_signal: bool,
+
+    #[arg(long)]
+    /// Log progress messages and warnings to stderr, along with each based number found
+    /// (at debug level), filtered by the RUST_LOG environment variable: off, error, warn,
+    /// info (the default), debug or trace. The results at the end still go to stdout.
+    log: bool,
+
+    #[arg(long)]
+    /// Print each based number to stdout as soon as it is found, as a line of JSON
+    /// like {"number":10,"basedness":6,"rank":5}, for consuming during a long run.
+    emit_records_live: bool,
+
+    #[arg(long)]
+    /// Print the info of the last this many numbers considered, regardless of any
+    /// filters, for spot-checking the end of a run.
+    tail: Option<usize>,
+
+    #[arg(long)]
+    /// Every this many numbers, print the based numbers found so far, without
+    /// waiting for the end of the run.
+    window: Option<NonZeroUsize>,
+
+    #[arg(long)]
+    /// Print how long was spent building the sieve, in the main loop, and writing output.
+    profile: bool,
+
+    #[arg(long)]
+    /// Before starting, print the sizes of the per-number arrays and their elements.
+    debug_sizes: bool,
+
+    #[arg(long, value_parser = parse_bytes)]
+    /// Refuse to start if the estimated memory usage exceeds this many bytes.
+    /// Accepts suffixes K, M, G and T (powers of 1024), e.g. 8G.
+    max_memory: Option<u64>,
+}
+
+impl Args {
+    /// Whether or not the info of every number is kept until the end of the run.
+    fn keeps_num_properties(&self) -> bool {
+        self.output_csv.is_some() && !self.parallel_output
+    }
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Time finding the based numbers up to a maximum with each sieve, to compare them.
+    /// Build with the count-allocations feature to also compare their peak heap usage
+    /// and allocation counts.
+    Bench {
+        #[arg(default_value_t = 10_000_000)]
+        /// The maximum number to check basedness of.
+        max_num: u64,
+    },
+    /// Check the based numbers up to a maximum against a reference list, such as an OEIS
+    /// b-file or a previous run's output, and report where they first differ.
+    VerifySequence {
+        /// The reference list: one based number per line, optionally after its index
+        /// (as in a b-file). Empty lines and lines starting with # are ignored.
+        reference: PathBuf,
+
+        #[arg(default_value_t = 10_000_000)]
+        /// The maximum number to check. Reference numbers larger than this are ignored.
+        max_num: u64,
+    },
+    /// Find several sequences of record-setting numbers up to a maximum in a single pass,
+    /// rather than a run for each.
+    Records {
+        #[arg(default_value_t = 10_000_000)]
+        /// The maximum number to check.
+        max_num: u64,
+
+        #[arg(long, value_delimiter = ',', value_parser = parse_record_kind, default_value = "basedness,hcn,abundant")]
+        /// Which sequences to find, separated by commas: basedness (the based numbers),
+        /// hcn (the highly composite numbers) and abundant (the superabundant numbers).
+        records: Vec<RecordKind>,
+    },
+    /// Compare two CSVs of number info, such as --output-csv from two versions or
+    /// configurations, and report the first row where they differ.
+    CompareRun {
+        /// The first CSV.
+        a: PathBuf,
+
+        /// The second CSV, which must have the same columns as the first.
+        b: PathBuf,
+    },
+    /// Print a CSV of the info of every column for a few hand-checkable numbers
+    /// (see FIXTURE_NUMBERS in the library), for testing against.
+    #[command(hide = true)]
+    Fixtures,
+    /// Summarize a CSV of number info written by a previous run, without rerunning the
+    /// sieve. Statistics which need a column the CSV doesn't have are left out.
+    Stats {
+        /// The CSV, such as an --output-csv.
+        path: PathBuf,
+    },
+    /// Find where a number falls in the based sequence: whether it is a based
+    /// number and which one, or otherwise the nearest based numbers around it.
+    Rank {
+        /// The number to look up.
+        number: u64,
+    },
+    /// Print the prime factorization of a number, along with d(n), ω(n) and its basedness.
+    /// If no number is given, numbers are read from stdin, one per line.
+    Factor {
+        /// The number to factorize, which must be positive.
+        #[arg(value_parser = parse_positive)]
+        number: Option<usize>,
+
+        #[arg(long)]
+        /// When reading from stdin, stop at the first line which isn't a number,
+        /// rather than skipping it with a warning.
+        strict: bool,
+    },
+}
+
+/// How to bucket values for a histogram.
+#[derive(Clone, Copy, Debug)]
+enum Bucketing {
+    /// Buckets of a fixed width, starting from 0.
+    Linear(u64),
+    /// Buckets between consecutive powers of 2, starting from 1.
+    Log2,
+    /// Buckets between consecutive powers of 10, starting from 1.
+    Log10,
+}
+
+impl Bucketing {
+    /// The index of the bucket containing `value`, which must be non-zero for
+    /// logarithmic buckets.
+    fn bucket(self, value: u64) -> usize {
+        match self {
+            Bucketing::Linear(width) => (value / width) as usize,
+            Bucketing::Log2 => value.ilog2() as usize,
+            Bucketing::Log10 => value.ilog10() as usize,
+        }
+    }
+
+    /// The range of values in the bucket at index `bucket`, as `(from, to)`, where
+    /// `from` is included and `to` is not.
+    fn bounds(self, bucket: usize) -> (u64, u64) {
+        let bucket = bucket as u32;
+        match self {
+            Bucketing::Linear(width) => (
+                width.saturating_mul(bucket.into()),
+                width.saturating_mul(u64::from(bucket) + 1),
+            ),
+            Bucketing::Log2 => (2u64.saturating_pow(bucket), 2u64.saturating_pow(bucket + 1)),
+            Bucketing::Log10 => (
+                10u64.saturating_pow(bucket),
+                10u64.saturating_pow(bucket + 1),
+            ),
+        }
+    }
+}
+
+/// Parses a [`Bucketing`]: `linear:WIDTH`, `log2` or `log10`.
+fn parse_bucketing(s: &str) -> Result<Bucketing, String> {
+    match s {
+        "log2" => Ok(Bucketing::Log2),
+        "log10" => Ok(Bucketing::Log10),
+        _ => match s.strip_prefix("linear:").map(str::parse) {
+            Some(Ok(0)) => Err("bucket width must be at least 1".to_string()),
+            Some(Ok(width)) => Ok(Bucketing::Linear(width)),
+            Some(Err(e)) => Err(format!("invalid bucket width: {e}")),
+            None => Err("expected linear:WIDTH, log2 or log10".to_string()),
+        },
+    }
 }
 
 #[derive(Clone, Copy, Debug, Serialize)]
@@ -31,87 +416,464 @@ struct NumProperties {
     basedness: u64,
 }
 
-/// A multiset of prime factors. Represented as a map of Prime -> Power.
-/// Backing storage of `TinyMap` ensures that as long as there are 3 or fewer
-/// prime factors for a number (which is true for ~62% of numbers),
-/// this will not need to allocate. To reduce allocations more, increase
-/// the size of the array part of the `TinyMap` - doing this will increase the
-/// amount of memory used for numbers with fewer than that many factors.
-/// For a backing storage array size of 3, there will not be any need for allocation
-/// for ~62% of numbers, but the average amount of memory used will be increased by
-/// ~22%
-type FactorMultiset = TinyMap<usize, u8, 3>;
+/// A column of [`NumProperties`] which can be output.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Column {
+    #[value(name = "number")]
+    Number,
+    #[value(name = "num_factors")]
+    NumFactors,
+    #[value(name = "num_prime_factors")]
+    NumPrimeFactors,
+    #[value(name = "basedness")]
+    Basedness,
+}
+
+impl Column {
+    /// The name of the column, as used in CSV headers.
+    fn name(self) -> &'static str {
+        match self {
+            Column::Number => "number",
+            Column::NumFactors => "num_factors",
+            Column::NumPrimeFactors => "num_prime_factors",
+            Column::Basedness => "basedness",
+        }
+    }
+
+    /// What the column holds, for documenting the output.
+    fn description(self) -> &'static str {
+        match self {
+            Column::Number => "The number n",
+            Column::NumFactors => {
+                "d(n), the number of divisors of n: the product of k + 1 over each prime power p^k in its factorization"
+            }
+            Column::NumPrimeFactors => {
+                "ω(n), the number of distinct prime factors of n: the number of primes in its factorization"
+            }
+            Column::Basedness => {
+                "The basedness of n: ω(n) · d(n - 1), which is 0 for 1. A based number is more based than all smaller numbers"
+            }
+        }
+    }
+
+    /// The value of this column for the given number.
+    fn value(self, props: &NumProperties) -> u64 {
+        match self {
+            Column::Number => props.number,
+            Column::NumFactors => props.num_factors,
+            Column::NumPrimeFactors => props.num_prime_factors,
+            Column::Basedness => props.basedness,
+        }
+    }
+}
 
 fn main() -> Result<(), Box<dyn Error>> {
     let args = Args::parse();
+    if args.log {
+        logging::init();
+    }
+
+    match args.command {
+        Some(Command::Bench { max_num }) => {
+            check_spf_sieve_limit(max_num, "bench")?;
+            bench(max_num);
+            Ok(())
+        }
+        Some(Command::VerifySequence { reference, max_num }) => {
+            check_spf_sieve_limit(max_num, "verify-sequence")?;
+            verify_sequence(&reference, max_num)
+        }
+        Some(Command::Records { max_num, records }) => {
+            check_spf_sieve_limit(max_num, "records")?;
+            print_records(max_num, &records);
+            Ok(())
+        }
+        Some(Command::CompareRun { a, b }) => compare_run(&a, &b),
+        Some(Command::Fixtures) => print_fixtures(),
+        Some(Command::Stats { path }) => stats(&path),
+        Some(Command::Rank { number }) => {
+            rank(number);
+            Ok(())
+        }
+        Some(Command::Factor {
+            number: Some(number),
+            ..
+        }) => {
+            print_factorization(number);
+            Ok(())
+        }
+        Some(Command::Factor {
+            number: None,
+            strict,
+        }) => {
+            for line in std::io::stdin().lines() {
+                let line = line?;
+                let line = line.trim();
+                if line.is_empty() {
+                    continue;
+                }
+
+                match parse_positive(line) {
+                    Ok(number) => print_factorization(number),
+                    Err(e) if strict => return Err(format!("invalid number {line:?}: {e}").into()),
+                    Err(e) => logging::warn(format_args!("skipping invalid number {line:?}: {e}")),
+                }
+            }
+            Ok(())
+        }
+        None => run(args),
+    }
+}
+
+fn run(args: Args) -> Result<(), Box<dyn Error>> {
+    let run_start = Instant::now();
+    if args.no_clone {
+        check_spf_sieve_limit(args.max_num, "--no-clone")?;
+    }
     let n = (args.max_num + 1) as usize;
 
-    let mut num_properties = vec![None; n];
-    let mut prime_factors = vec![FactorMultiset::new(); n];
-    let mut primes = Vec::new();
-    let mut based = Vec::new();
+    if args.help_metrics {
+        for column in Column::value_variants() {
+            println!("{}: {}", column.name(), column.description());
+        }
+        return Ok(());
+    }
+
+    if args.debug_sizes {
+        print_sizes(&args);
+    }
+
+    if let Some(path) = &args.json_schema {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, json_schema(&args.columns))?;
+    }
+
+    if let Some(max_memory) = args.max_memory {
+        let estimate = estimated_memory(&args);
+        if estimate > max_memory {
+            return Err(format!(
+                "estimated memory usage of {estimate} bytes exceeds --max-memory of {max_memory} bytes"
+            )
+            .into());
+        }
+    }
+
+    #[cfg(feature = "signals")]
+    if args.dump_state_on_signal {
+        signals::install();
+    }
+
+    // Per-number properties are only retained if they are going to be output at the end
+    let mut num_properties = args.keeps_num_properties().then(|| Vec::with_capacity(n));
+    // ...otherwise, with --parallel-output, they are sent to a thread which writes them
+    let mut output_thread = match &args.output_csv {
+        Some(path) if args.parallel_output => {
+            Some(spawn_output_thread(path, args.columns.clone())?)
+        }
+        _ => None,
+    };
+    let known_primes = match &args.primes_in {
+        Some(path) => read_primes(path, args.max_num)?,
+        None => Vec::new(),
+    };
+    let sieve_start = Instant::now();
+    let mut sieve: Box<dyn Sieve> = if args.no_clone {
+        Box::new(SpfSieve::new(n))
+    } else {
+        Box::new(CloneSieve::with_primes(n, known_primes))
+    };
+    let sieve_time = sieve_start.elapsed();
+    let (resumed, resumed_up_to) = match &args.resume_from_csv {
+        Some(path) => resume_based(path, args.max_num)?,
+        None => (Records::new(), 0),
+    };
+    // The resumed records are only added to the based numbers as the loop reaches them,
+    // so that everything which depends on the based numbers so far still sees them in order
+    let mut resumed = resumed.as_slice().iter().copied().peekable();
+    let mut based = Records::new();
+    let mut anti_based = WindowMinima::new(args.anti_based_window);
+    let mut perfect = Vec::new();
+    let mut smooth = Vec::new();
+    if args.smooth.is_some() && n > 1 {
+        smooth.push(1);
+    }
+    let mut abundancy_records = Records::new();
+    if args.abundancy {
+        // σ(1) = 1
+        abundancy_records.consider(
+            1,
+            Abundancy {
+                sum_of_divisors: 1,
+                number: 1,
+            },
+        );
+    }
 
     let mut num_prime_factors_histogram = [0; 10];
+    let mut largest_prime_factor_histogram = [0u64; usize::BITS as usize];
+    let mut basedness_histogram: Vec<u64> = Vec::new();
 
-    num_properties[1] = Some(NumProperties {
-        number: 1,
-        num_factors: 1,
-        num_prime_factors: 0,
-        basedness: 0,
-    });
+    let mut checksum = args.checksum.then(|| Writer::from_writer(Fnv1a::new()));
 
-    for i in progress_bar(2..n) {
-        // Find a prime factor of i - it must necessarily be one of the primes we have already found,
-        // or i is itself a prime
-        let p = primes
-            .iter()
-            .copied()
-            // If i is non-prime, then one of its factors must be no larger than sqrt(i)
-            .take_while(|p| p * p <= i)
-            .find(|p| i % p == 0);
-
-        let num_factors =
-        // If we found some small (< i) prime factor p
-        if let Some(p) = p {
-            // All factors of i / p are also factors of i
-            prime_factors[i] = prime_factors[i / p].clone();
-
-            // The power of p in the prime factor representation of i is
-            // 1 + the power of p in the prime factor representation of i / p
-            prime_factors[i]
-                .entry(p)
-                .and_modify(|k| *k += 1)
-                .or_insert(1);
-
-            // Definition of d(n) the divisor function
-            prime_factors[i].values().copied().map(|k| u64::from(k + 1)).product()
-        // Otherwise, i must be a prime
-        } else {
-            prime_factors[i].insert(i, 1);
-            primes.push(i);
+    let basedness_range = args.basedness_min.unwrap_or(0)..=args.basedness_max.unwrap_or(u64::MAX);
+    let only = args
+        .only
+        .as_ref()
+        .map(|path| read_numbers(path, args.max_num))
+        .transpose()?;
 
-            // All prime numbers have 2 factors: 1 and itself
-            2
-        };
-        prime_factors[i].shrink_to_fit();
+    // Everything which needs to see the properties of each number, in order
+    let mut factorization_csv = match &args.factorization_csv {
+        Some(path) => {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            let mut wtr = Writer::from_path(path)?;
+            wtr.write_record(["number", "factorization"])?;
+            Some(wtr)
+        }
+        None => None,
+    };
+
+    // The info of the last --tail numbers
+    let mut tail = VecDeque::with_capacity(args.tail.unwrap_or(0));
+
+    let mut emit = |props: NumProperties, factors: &FactorMultiset| -> Result<(), csv::Error> {
+        // The checksum and the tail cover every number, regardless of which are output
+        if let Some(checksum) = &mut checksum {
+            checksum.serialize(props)?;
+        }
+        if let Some(len) = args.tail
+            && len > 0
+        {
+            if tail.len() == len {
+                tail.pop_front();
+            }
+            tail.push_back(props);
+        }
+
+        if !basedness_range.contains(&props.basedness) {
+            return Ok(());
+        }
+        if let Some(only) = &only
+            && !only.contains(&props.number)
+        {
+            return Ok(());
+        }
+
+        if let Some(num_properties) = &mut num_properties {
+            num_properties.push(props);
+        }
+        if let Some(output_thread) = &mut output_thread {
+            output_thread.send(props);
+        }
+        if let Some(wtr) = &mut factorization_csv {
+            wtr.serialize((props.number, format_factorization(factors)))?;
+        }
+        Ok(())
+    };
+
+    emit(
+        NumProperties {
+            number: 1,
+            num_factors: 1,
+            num_prime_factors: 0,
+            basedness: 0,
+        },
+        &FactorMultiset::new(),
+    )?;
+
+    // Basedness of i only depends on d(i - 1), so that's all we need to keep around.
+    // d(1) = 1
+    let mut prev_num_factors = 1;
 
-        let num_prime_factors = prime_factors[i].len() as u64;
+    // How many numbers had a factorization which didn't fit inline
+    #[cfg(feature = "count-allocations")]
+    let mut heap_factorizations = 0;
+
+    // π(x), and the rows of --counts-csv as (x, π(x), number of based numbers up to x)
+    let mut num_primes_so_far = 0;
+    let mut counts = Vec::new();
+
+    // Where the loop stopped, if it stopped early because of --stop-at-basedness,
+    // --time-limit or a signal
+    let mut stopped_at = None;
+
+    let loop_start = Instant::now();
+    let numbers = progress_bar(2..n);
+    let pb = numbers.progress.clone();
+    for i in numbers {
+        let factors = sieve.factorize(i);
+        let num_factors = num_factors(factors);
+        let num_prime_factors = factors.len() as u64;
+
+        #[cfg(feature = "count-allocations")]
+        if !factors.is_inline() {
+            heap_factorizations += 1;
+        }
+
+        if args.abundancy {
+            let abundancy = Abundancy {
+                sum_of_divisors: sum_of_divisors(factors),
+                number: i as u64,
+            };
+            if abundancy.is_perfect() {
+                perfect.push(i);
+            }
+            abundancy_records.consider(i as u64, abundancy);
+        }
+
+        if let Some(bound) = args.smooth
+            && factors.keys().next_back().is_some_and(|&p| p <= bound)
+        {
+            smooth.push(i);
+        }
 
         num_prime_factors_histogram[num_prime_factors as usize - 1] += 1;
 
-        let basedness = num_prime_factors * num_properties[i - 1].unwrap().num_factors;
-        num_properties[i] = Some(NumProperties {
-            number: i as u64,
-            num_factors,
-            num_prime_factors,
-            basedness,
-        });
+        if args.largest_prime_factor_csv.is_some()
+            && let Some(&p) = factors.keys().next_back()
+        {
+            largest_prime_factor_histogram[p.ilog2() as usize] += 1;
+        }
+
+        let basedness = num_prime_factors * prev_num_factors;
+        prev_num_factors = num_factors;
+
+        // Every number from 2 has a prime factor, so a basedness of at least 1
+        if args.basedness_csv.is_some() {
+            let bucket = args.basedness_buckets.bucket(basedness);
+            if bucket >= basedness_histogram.len() {
+                basedness_histogram.resize(bucket + 1, 0);
+            }
+            basedness_histogram[bucket] += 1;
+        }
+
+        emit(
+            NumProperties {
+                number: i as u64,
+                num_factors,
+                num_prime_factors,
+                basedness,
+            },
+            factors,
+        )?;
+
+        // A based number is one which is more based than all smaller numbers. Numbers
+        // covered by --resume-from-csv have already been considered
+        let is_based = if i as u64 <= resumed_up_to {
+            resumed
+                .next_if(|&(number, _)| number == i as u64)
+                .is_some_and(|(number, basedness)| based.consider(number, basedness))
+        } else {
+            based.consider(i as u64, basedness)
+        };
+
+        // Stdout is line buffered, so each line is flushed as soon as it is printed
+        if is_based && args.emit_records_live {
+            pb.suspend(|| {
+                println!(
+                    r#"{{"number":{i},"basedness":{basedness},"rank":{}}}"#,
+                    based.len()
+                )
+            });
+        }
+
+        if is_based && logging::enabled(Level::Debug) {
+            pb.suspend(|| {
+                logging::log(
+                    Level::Debug,
+                    format_args!("Based number {i}, with basedness {basedness}"),
+                )
+            });
+        }
+
+        if let Some(window) = args.window
+            && i % window == 0
+            && let Some((number, basedness)) = based.last()
+        {
+            report(
+                &pb,
+                format_args!(
+                    "Up to {i}: {} based numbers, the latest being {number} with basedness {basedness}",
+                    based.len()
+                ),
+            );
+        }
+
+        // All prime numbers have 2 factors: 1 and itself
+        if args.anti_based && num_factors > 2 {
+            anti_based.consider(i as u64, basedness);
+        }
+
+        if args.counts_csv.is_some() {
+            if num_factors == 2 {
+                num_primes_so_far += 1;
+            }
+            if i % args.interval == 0 {
+                counts.push((i, num_primes_so_far, based.len()));
+            }
+        }
+
+        if let Some(stop) = args.stop_at_basedness
+            && is_based
+            && basedness >= stop
+        {
+            report(
+                &pb,
+                format_args!(
+                    "Stopped at {i} of {}, the first based number with basedness at least {stop} (it has {basedness})",
+                    args.max_num
+                ),
+            );
+            stopped_at = Some(i);
+            break;
+        }
 
-        // A based number is one which is more based than all smaller numbers
-        if basedness > based.last().copied().map_or(0, |(_, basedness)| basedness) {
-            based.push((i, basedness));
+        // Checking the time is much slower than a loop iteration, so only check it now and then
+        if let Some(time_limit) = args.time_limit
+            && i % 4096 == 0
+            && run_start.elapsed() >= time_limit
+        {
+            report(
+                &pb,
+                format_args!(
+                    "Reached --time-limit at {i} of {}, outputting everything up to it",
+                    args.max_num
+                ),
+            );
+            stopped_at = Some(i);
+            break;
         }
+
+        #[cfg(feature = "signals")]
+        if args.dump_state_on_signal && signals::interrupted() {
+            report(
+                &pb,
+                format_args!(
+                    "Interrupted at {i} of {}, outputting everything up to it",
+                    args.max_num
+                ),
+            );
+            stopped_at = Some(i);
+            break;
+        }
+    }
+    pb.finish_and_clear();
+    if let Some(mut wtr) = factorization_csv {
+        wtr.flush()?;
+    }
+    if let Some(output_thread) = output_thread {
+        output_thread.finish()?;
     }
+    let loop_time = loop_start.elapsed();
+    let output_start = Instant::now();
+
+    // Some sieves find primes past where the loop stopped, which are left out of the output
+    let last = stopped_at.unwrap_or(n - 1);
+    let num_primes = sieve.primes().partition_point(|&p| p <= last);
 
     let num_prime_factors_histogram = num_prime_factors_histogram
         .iter()
@@ -123,19 +885,71 @@ fn main() -> Result<(), Box<dyn Error>> {
         .map(|(i, n)| (i + 1, n))
         .collect::<Vec<_>>();
 
-    println!("Based numbers:");
-    println!("{based:?}");
+    if args.count_only {
+        println!("Based numbers: {}", based.len());
+        println!("Primes: {num_primes}");
+        if let Some((number, basedness)) = based.last() {
+            println!("Largest basedness: {basedness} (first reached by {number})");
+        }
+    } else {
+        println!("Based numbers:");
+        println!("{:?}", based.as_slice());
+    }
     println!("Prime factor histogram:");
     println!("{num_prime_factors_histogram:?}",);
 
-    if let Some(path) = args.output_csv {
+    if args.anti_based {
+        println!("Anti-based numbers:");
+        println!("{:?}", anti_based.as_slice());
+    }
+
+    if args.abundancy {
+        println!("Perfect numbers:");
+        println!("{perfect:?}");
+        println!("Abundancy records (number, σ(n)):");
+        println!(
+            "{:?}",
+            abundancy_records
+                .as_slice()
+                .iter()
+                .map(|(number, abundancy)| (number, abundancy.sum_of_divisors))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    if let Some(bound) = args.smooth
+        && args.smooth_csv.is_none()
+    {
+        println!("{bound}-smooth numbers:");
+        println!("{smooth:?}");
+    }
+
+    if args.tail.is_some() {
+        println!("Last {} numbers:", tail.len());
+        for props in &tail {
+            println!(
+                "{}: d(n)={} ω(n)={} basedness={}",
+                props.number, props.num_factors, props.num_prime_factors, props.basedness
+            );
+        }
+    }
+
+    if let Some(checksum) = checksum {
+        let checksum = checksum.into_inner().map_err(|e| e.into_error())?;
+        println!("Checksum: {:016x}", checksum.hash);
+    }
+
+    if let Some(path) = args.output_csv
+        && !args.parallel_output
+    {
         if let Some(dir) = path.parent() {
             std::fs::create_dir_all(dir)?;
         }
         let mut wtr = Writer::from_path(path)?;
+        wtr.write_record(args.columns.iter().map(|c| c.name()))?;
 
         for prop in num_properties.iter().flatten() {
-            wtr.serialize(prop)?;
+            wtr.write_record(args.columns.iter().map(|c| c.value(prop).to_string()))?;
         }
     }
 
@@ -150,10 +964,918 @@ fn main() -> Result<(), Box<dyn Error>> {
         }
     }
 
+    if let Some(path) = args.histogram_png {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let counts: Vec<u64> = num_prime_factors_histogram
+            .iter()
+            .map(|&(_, n)| n)
+            .collect();
+        png::write_bar_chart(&path, &counts)?;
+    }
+
+    if let Some(path) = args.basedness_csv {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut wtr = Writer::from_path(path)?;
+        // Each bucket holds basedness from `basedness_from` up to but not including `basedness_to`
+        wtr.write_record(["basedness_from", "basedness_to", "count"])?;
+
+        for (bucket, count) in basedness_histogram.iter().enumerate() {
+            let (from, to) = args.basedness_buckets.bounds(bucket);
+            wtr.serialize((from, to, count))?;
+        }
+    }
+
+    if let Some(path) = args.largest_prime_factor_csv {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut wtr = Writer::from_path(path)?;
+        wtr.write_record(["log2_bucket", "count"])?;
+
+        // Every prime is at least 2^1, and buckets past the largest prime factor are empty
+        let used = largest_prime_factor_histogram
+            .iter()
+            .rposition(|&count| count > 0)
+            .map_or(0, |last| last + 1);
+        for (bucket, count) in largest_prime_factor_histogram
+            .iter()
+            .enumerate()
+            .take(used)
+            .skip(1)
+        {
+            wtr.serialize((bucket, count))?;
+        }
+    }
+
+    if let Some(path) = args.prime_csv {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut wtr = Writer::from_path(path)?;
+        wtr.write_record(["prime"])?;
+
+        for &p in &sieve.primes()[..num_primes] {
+            wtr.serialize(p)?;
+        }
+    }
+
+    if let Some(path) = args.prime_gap_csv {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut wtr = Writer::from_path(path)?;
+        wtr.write_record(["prime", "gap"])?;
+
+        // The last prime's gap is unknown, so it is left out
+        for pair in sieve.primes()[..num_primes].windows(2) {
+            wtr.serialize((pair[0], pair[1] - pair[0]))?;
+        }
+    }
+
+    if let Some(path) = args.jumps_csv {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut wtr = Writer::from_path(path)?;
+        wtr.write_record(["number", "basedness", "delta", "ratio"])?;
+
+        for pair in based.as_slice().windows(2) {
+            let ((_, prev), (number, basedness)) = (pair[0], pair[1]);
+            wtr.serialize((
+                number,
+                basedness,
+                basedness - prev,
+                basedness as f64 / prev as f64,
+            ))?;
+        }
+    }
+
+    if let Some(path) = args.counts_csv {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut wtr = Writer::from_path(path)?;
+        wtr.write_record(["x", "pi_x", "based_count"])?;
+
+        if counts.last().is_none_or(|&(x, _, _)| x != last) {
+            counts.push((last, num_primes_so_far, based.len()));
+        }
+        for row in counts {
+            wtr.serialize(row)?;
+        }
+    }
+
+    if let Some(path) = args.smooth_csv {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut wtr = Writer::from_path(path)?;
+        wtr.write_record(["number"])?;
+
+        for number in smooth {
+            wtr.serialize(number)?;
+        }
+    }
+
+    if let Some(path) = args.divisors_csv {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut wtr = Writer::from_path(path)?;
+        wtr.write_record(["number", "divisors"])?;
+
+        let mut skipped = 0;
+        // Only the numbers the loop got to, in case it stopped early
+        for i in 1..(last + 1).min((args.divisors_below as usize).saturating_add(1)) {
+            let factors = sieve.factorize(i);
+            if num_factors(factors) > args.max_divisors {
+                skipped += 1;
+                continue;
+            }
+
+            let divisors = divisors(factors)
+                .iter()
+                .map(u64::to_string)
+                .collect::<Vec<_>>()
+                .join(" ");
+            wtr.serialize((i, divisors))?;
+        }
+
+        if skipped > 0 {
+            logging::warn(format_args!(
+                "skipped divisors of {skipped} numbers with more than {} divisors",
+                args.max_divisors
+            ));
+        }
+    }
+
+    #[cfg(feature = "count-allocations")]
+    {
+        println!("Allocations: {}", counting_allocator::allocations());
+        // The SPF sieve reuses a single factorization, so this is only meaningful when
+        // each number has its own. The numbers factorized were 2 to `last`, if any.
+        if !args.no_clone && last > 1 {
+            println!(
+                "Numbers with heap-allocated factorizations: {heap_factorizations} ({:.1}%)",
+                100.0 * heap_factorizations as f64 / (last - 1) as f64
+            );
+        }
+    }
+
+    if args.profile {
+        println!("Profile:");
+        println!("Building sieve: {:.3}s", sieve_time.as_secs_f64());
+        println!("Main loop: {:.3}s", loop_time.as_secs_f64());
+        println!("Output: {:.3}s", output_start.elapsed().as_secs_f64());
+    }
+
+    Ok(())
+}
+
+/// How many numbers' info can be waiting for the output thread before calculation
+/// waits for it to catch up.
+const OUTPUT_CHANNEL_CAPACITY: usize = 1 << 16;
+
+/// The thread writing --output-csv with --parallel-output, along with the sender of the
+/// number info for it to write. Dropping it, such as when a run returns early with an
+/// error, still waits for the thread to write everything it has been sent.
+struct OutputThread {
+    sender: Option<SyncSender<NumProperties>>,
+    thread: Option<JoinHandle<csv::Result<()>>>,
+}
+
+impl OutputThread {
+    /// Sends number info to be written.
+    fn send(&mut self, props: NumProperties) {
+        // Sending only fails if the thread has stopped because of an error, which is
+        // reported by `finish`
+        if let Some(sender) = &self.sender
+            && sender.send(props).is_err()
+        {
+            self.sender = None;
+        }
+    }
+
+    /// Waits for the thread to write everything it has been sent, returning the first
+    /// error it ran into, if any.
+    fn finish(mut self) -> csv::Result<()> {
+        self.hang_up()
+            .map_or(Ok(()), |result| result.expect("output thread panicked"))
+    }
+
+    /// Drops the sender, which lets the thread finish, and joins it if it hasn't been yet.
+    fn hang_up(&mut self) -> Option<thread::Result<csv::Result<()>>> {
+        self.sender = None;
+        self.thread.take().map(JoinHandle::join)
+    }
+}
+
+impl Drop for OutputThread {
+    fn drop(&mut self) {
+        // Only reached without `finish` when another error is already being returned
+        self.hang_up();
+    }
+}
+
+/// Starts a thread which writes the given columns of the number info it is sent
+/// to a CSV at `path`, for --parallel-output. The thread finishes once it is
+/// [finished](OutputThread::finish) or dropped.
+fn spawn_output_thread(
+    path: &PathBuf,
+    columns: Vec<Column>,
+) -> Result<OutputThread, Box<dyn Error>> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    // Opening the file up front reports any error before the run starts
+    let mut wtr = Writer::from_path(path)?;
+    let (sender, receiver) = mpsc::sync_channel(OUTPUT_CHANNEL_CAPACITY);
+
+    let thread = thread::spawn(move || {
+        wtr.write_record(columns.iter().map(|c| c.name()))?;
+        for prop in receiver {
+            wtr.write_record(columns.iter().map(|c| c.value(&prop).to_string()))?;
+        }
+        Ok(wtr.flush()?)
+    });
+    Ok(OutputThread {
+        sender: Some(sender),
+        thread: Some(thread),
+    })
+}
+
+/// A 64-bit FNV-1a hash of everything written to it.
+struct Fnv1a {
+    hash: u64,
+}
+
+impl Fnv1a {
+    fn new() -> Self {
+        Self {
+            hash: 0xcbf29ce484222325,
+        }
+    }
+}
+
+impl std::io::Write for Fnv1a {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        for &byte in buf {
+            self.hash ^= u64::from(byte);
+            self.hash = self.hash.wrapping_mul(0x100000001b3);
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Reads a file of numbers, one per line, for --only. Warns about any numbers larger
+/// than `max_num`, since they will never be output.
+fn read_numbers(path: &PathBuf, max_num: u64) -> Result<HashSet<u64>, Box<dyn Error>> {
+    let mut numbers = HashSet::new();
+    for line in std::fs::read_to_string(path)?.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        numbers.insert(
+            line.parse()
+                .map_err(|e| format!("invalid number {line:?} in {}: {e}", path.display()))?,
+        );
+    }
+
+    let too_large = numbers.iter().filter(|&&number| number > max_num).count();
+    if too_large > 0 {
+        logging::warn(format_args!(
+            "{too_large} numbers in {} are larger than {max_num}, and won't be output",
+            path.display()
+        ));
+    }
+    Ok(numbers)
+}
+
+/// Reads a list of primes, one per line, ignoring any larger than `max_num`. A `prime`
+/// header line (as in --prime-csv) is skipped. The primes must be in increasing order,
+/// and must be every prime up to the largest of them - which is checked with a sieve of
+/// Eratosthenes, much cheaper than the trial division the list saves.
+fn read_primes(path: &PathBuf, max_num: u64) -> Result<Vec<usize>, Box<dyn Error>> {
+    let mut primes: Vec<usize> = Vec::new();
+    for line in std::fs::read_to_string(path)?.lines() {
+        let line = line.trim();
+        if line.is_empty() || line == "prime" {
+            continue;
+        }
+        let p: usize = line
+            .parse()
+            .map_err(|e| format!("invalid number {line:?} in {}: {e}", path.display()))?;
+        if p < 2 {
+            return Err(format!("{} lists {p}, which isn't prime", path.display()).into());
+        }
+        if let Some(&last) = primes.last()
+            && p <= last
+        {
+            return Err(format!(
+                "{} is not in increasing order: {p} comes after {last}",
+                path.display()
+            )
+            .into());
+        }
+        if p as u64 > max_num {
+            break;
+        }
+        primes.push(p);
+    }
+
+    let Some(&largest) = primes.last() else {
+        return Ok(primes);
+    };
+    let mut is_composite = vec![false; largest + 1];
+    let mut listed = primes.iter().copied().peekable();
+    for i in 2..=largest {
+        let is_listed = listed.next_if_eq(&i).is_some();
+        if is_composite[i] {
+            if is_listed {
+                return Err(format!("{} lists {i}, which isn't prime", path.display()).into());
+            }
+            continue;
+        }
+        if !is_listed {
+            return Err(format!("{} is missing the prime {i}", path.display()).into());
+        }
+        for multiple in (i * i..=largest).step_by(i) {
+            is_composite[multiple] = true;
+        }
+    }
+    Ok(primes)
+}
+
+/// Reads the based numbers from a CSV of number info written by a previous run,
+/// ignoring any numbers larger than `max_num`. Returns the based numbers, along with
+/// the largest number they account for.
+fn resume_based(path: &PathBuf, max_num: u64) -> Result<(Records, u64), Box<dyn Error>> {
+    let mut rdr = csv::Reader::from_path(path)?;
+    let headers = rdr.headers()?;
+    let column = |name: &str| {
+        headers
+            .iter()
+            .position(|h| h == name)
+            .ok_or_else(|| format!("{} has no {name} column", path.display()))
+    };
+    let (number_column, basedness_column) = (column("number")?, column("basedness")?);
+
+    let mut based = Records::new();
+    let mut up_to = 0;
+    for record in rdr.records() {
+        let record = record?;
+        let number: u64 = record[number_column].parse()?;
+        let basedness: u64 = record[basedness_column].parse()?;
+
+        // A missing number could have been a based number
+        if number != up_to + 1 {
+            return Err(format!(
+                "{} is missing number {} - was it written with a basedness filter?",
+                path.display(),
+                up_to + 1
+            )
+            .into());
+        }
+        if number > max_num {
+            break;
+        }
+        up_to = number;
+
+        // Based numbers start from 2, as in the main loop
+        if number >= 2 {
+            based.consider(number, basedness);
+        }
+    }
+
+    Ok((based, up_to))
+}
+
+/// An estimate of the peak memory usage of a run, in bytes. Only counts the
+/// per-number arrays, which dominate for any reasonably large `max_num`.
+fn estimated_memory(args: &Args) -> u64 {
+    let n = args.max_num + 1;
+    let mut per_number = if args.no_clone {
+        size_of::<u32>()
+    } else {
+        size_of::<FactorMultiset>()
+    };
+    if args.keeps_num_properties() {
+        per_number += size_of::<NumProperties>();
+    }
+    n.saturating_mul(per_number as u64)
+}
+
+/// A JSON Schema for one row of number info with the given columns. Written by hand,
+/// since every column is a non-negative integer with a fixed name and description,
+/// none of which need escaping.
+fn json_schema(columns: &[Column]) -> String {
+    let properties = columns
+        .iter()
+        .map(|c| {
+            format!(
+                r#"    "{}": {{ "type": "integer", "minimum": 0, "description": "{}" }}"#,
+                c.name(),
+                c.description()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(",\n");
+    let required = columns
+        .iter()
+        .map(|c| format!(r#""{}""#, c.name()))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    format!(
+        r#"{{
+  "$schema": "https://json-schema.org/draft/2020-12/schema",
+  "title": "NumProperties",
+  "description": "The calculated info of a single number",
+  "type": "object",
+  "properties": {{
+{properties}
+  }},
+  "required": [{required}],
+  "additionalProperties": false
+}}
+"#
+    )
+}
+
+/// Prints the sizes behind [`estimated_memory`]: each per-number array, and the
+/// elements it is made of.
+fn print_sizes(args: &Args) {
+    let n = args.max_num + 1;
+    let array = |name: &str, element: &str, size: usize| {
+        println!(
+            "{name}: {n} x {size} bytes ({element}) = {} bytes",
+            n.saturating_mul(size as u64)
+        )
+    };
+
+    println!("Sizes:");
+    if args.no_clone {
+        array("Smallest prime factors", "u32", size_of::<u32>());
+    } else {
+        array(
+            "Prime factorizations",
+            "FactorMultiset",
+            size_of::<FactorMultiset>(),
+        );
+        println!(
+            "    up to {} prime factors inline, otherwise on the heap",
+            FactorMultiset::new().capacity()
+        );
+    }
+    if args.keeps_num_properties() {
+        array("Number info", "NumProperties", size_of::<NumProperties>());
+    } else {
+        println!("Number info: not kept (no --output-csv, or --parallel-output)");
+    }
+    println!("Estimated total: {} bytes", estimated_memory(args));
+}
+
+/// Parses a number of bytes, with an optional binary suffix (K, M, G or T),
+/// e.g. `512M` or `8G`.
+fn parse_bytes(s: &str) -> Result<u64, String> {
+    let s = s.trim().trim_end_matches(['B', 'b']).trim_end_matches('i');
+    let (digits, shift) = match s.char_indices().last() {
+        Some((i, 'K' | 'k')) => (&s[..i], 10),
+        Some((i, 'M' | 'm')) => (&s[..i], 20),
+        Some((i, 'G' | 'g')) => (&s[..i], 30),
+        Some((i, 'T' | 't')) => (&s[..i], 40),
+        _ => (s, 0),
+    };
+    let bytes: u64 = digits.trim().parse().map_err(|e| format!("{e}"))?;
+    bytes
+        .checked_mul(1 << shift)
+        .ok_or_else(|| "size is too large".to_string())
+}
+
+/// Parses a duration, with an optional suffix of s, m or h, e.g. `30s` or `5m`.
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let (digits, unit) = match s.char_indices().last() {
+        Some((i, 's')) => (&s[..i], 1),
+        Some((i, 'm')) => (&s[..i], 60),
+        Some((i, 'h')) => (&s[..i], 60 * 60),
+        _ => (s, 1),
+    };
+    let amount: f64 = digits.trim().parse().map_err(|e| format!("{e}"))?;
+    Duration::try_from_secs_f64(amount * f64::from(unit)).map_err(|e| format!("{e}"))
+}
+
+/// Checks that an [`SpfSieve`] can be built for the numbers up to `max_num`, returning an
+/// error naming the limit, and `what` is limited by it, if not.
+fn check_spf_sieve_limit(max_num: u64, what: &str) -> Result<(), Box<dyn Error>> {
+    // The sieve covers the numbers below `max_num + 1`
+    let limit = SpfSieve::MAX_N as u64 - 1;
+    if max_num > limit {
+        return Err(
+            format!("{what} supports a maximum number of at most {limit}, not {max_num}").into(),
+        );
+    }
+    Ok(())
+}
+
+/// Finds the based numbers up to `max_num` with each sieve, reporting how long
+/// each took (including building the sieve) and, when counting allocations,
+/// how much memory each needed.
+fn bench(max_num: u64) {
+    let n = (max_num + 1) as usize;
+    let mut results = Vec::new();
+    for no_clone in [false, true] {
+        #[cfg(feature = "count-allocations")]
+        let allocations = {
+            counting_allocator::reset_peak();
+            counting_allocator::allocations()
+        };
+
+        let start = Instant::now();
+        let (name, mut sieve): (_, Box<dyn Sieve>) = if no_clone {
+            ("SPF sieve (--no-clone)", Box::new(SpfSieve::new(n)))
+        } else {
+            ("clone sieve (default)", Box::new(CloneSieve::new(n)))
+        };
+        let based = based_numbers_below(sieve.as_mut(), n);
+        let elapsed = start.elapsed();
+
+        println!(
+            "{name}: {:.3}s, {} based numbers",
+            elapsed.as_secs_f64(),
+            based.len()
+        );
+        #[cfg(feature = "count-allocations")]
+        println!(
+            "    peak heap usage: {} bytes, allocations: {}",
+            counting_allocator::peak_bytes(),
+            counting_allocator::allocations() - allocations
+        );
+
+        // Measure the next sieve without this one still allocated
+        drop(sieve);
+        results.push(based);
+    }
+
+    if results
+        .windows(2)
+        .any(|w| w[0].as_slice() != w[1].as_slice())
+    {
+        logging::warn(format_args!("the sieves found different based numbers"));
+    }
+}
+
+/// Compares two CSVs row by row, printing how many rows differ and the first difference.
+/// Fails if any rows differ, or if one CSV has more rows than the other.
+fn compare_run(a: &PathBuf, b: &PathBuf) -> Result<(), Box<dyn Error>> {
+    let mut rdr_a = csv::Reader::from_path(a)?;
+    let mut rdr_b = csv::Reader::from_path(b)?;
+    let headers = rdr_a.headers()?.clone();
+    if &headers != rdr_b.headers()? {
+        return Err(format!(
+            "{} has columns {:?}, but {} has columns {:?}",
+            a.display(),
+            headers.iter().collect::<Vec<_>>(),
+            b.display(),
+            rdr_b.headers()?.iter().collect::<Vec<_>>()
+        )
+        .into());
+    }
+
+    let mut records_a = rdr_a.records();
+    let mut records_b = rdr_b.records();
+    let mut compared = 0;
+    let mut differing = 0;
+    let mut first_difference = None;
+    let (len_a, len_b) = loop {
+        match (records_a.next().transpose()?, records_b.next().transpose()?) {
+            (Some(row_a), Some(row_b)) => {
+                compared += 1;
+                if row_a != row_b {
+                    differing += 1;
+                    first_difference.get_or_insert((compared, row_a, row_b));
+                }
+            }
+            // Count what's left of the longer CSV
+            (Some(_), None) => break (compared + 1 + records_a.count(), compared),
+            (None, Some(_)) => break (compared, compared + 1 + records_b.count()),
+            (None, None) => break (compared, compared),
+        }
+    };
+
+    println!("Compared {compared} rows, of which {differing} differ");
+    if let Some((row, row_a, row_b)) = &first_difference {
+        println!("First difference is at row {row}:");
+        for ((column, field_a), field_b) in headers.iter().zip(row_a).zip(row_b) {
+            if field_a != field_b {
+                println!(
+                    "  {column}: {field_a} in {}, {field_b} in {}",
+                    a.display(),
+                    b.display()
+                );
+            }
+        }
+    }
+    if len_a != len_b {
+        println!(
+            "{} has {len_a} rows, but {} has {len_b} rows",
+            a.display(),
+            b.display()
+        );
+    }
+
+    if first_difference.is_some() || len_a != len_b {
+        return Err(format!("{} and {} differ", a.display(), b.display()).into());
+    }
+    Ok(())
+}
+
+/// Aggregate statistics of a CSV of number info, as printed by the stats subcommand.
+/// Each statistic is `None` if the CSV doesn't have the columns it needs.
+#[derive(Debug, Default, PartialEq)]
+struct Stats {
+    numbers: u64,
+    based_numbers: Option<usize>,
+    /// The largest basedness, along with the first number which reached it, if known
+    largest_basedness: Option<(u64, Option<u64>)>,
+    /// (ω(n), how many numbers have it), for each ω(n) of at least 1 which any number has
+    num_prime_factors_histogram: Option<Vec<(usize, u64)>>,
+    mean_num_factors: Option<f64>,
+}
+
+/// Finds the aggregate statistics of a CSV of number info, in a single pass over it.
+fn collect_stats(rdr: impl std::io::Read) -> Result<Stats, Box<dyn Error>> {
+    let mut rdr = csv::Reader::from_reader(rdr);
+    let headers = rdr.headers()?;
+    let column = |column: Column| headers.iter().position(|h| h == column.name());
+    let number_column = column(Column::Number);
+    let num_factors_column = column(Column::NumFactors);
+    let num_prime_factors_column = column(Column::NumPrimeFactors);
+    let basedness_column = column(Column::Basedness);
+
+    let mut count = 0u64;
+    let mut based = Records::new();
+    let mut largest_basedness: Option<(u64, Option<u64>)> = None;
+    let mut num_prime_factors_histogram: Vec<u64> = Vec::new();
+    let mut num_factors_sum = 0u128;
+    // Whether or not the rows so far are every number from 1, as in resume_based
+    let mut every_number = true;
+
+    for record in rdr.records() {
+        let record = record?;
+        let field = |column: Option<usize>| -> Result<Option<u64>, Box<dyn Error>> {
+            column.map(|i| Ok(record[i].parse()?)).transpose()
+        };
+        let number = field(number_column)?;
+        count += 1;
+        // A missing number could have been a based number
+        every_number &= number == Some(count);
+
+        if let Some(basedness) = field(basedness_column)? {
+            if largest_basedness.is_none_or(|(largest, _)| basedness > largest) {
+                largest_basedness = Some((basedness, number));
+            }
+            // Based numbers start from 2, as in the main loop
+            if let Some(number) = number
+                && number >= 2
+            {
+                based.consider(number, basedness);
+            }
+        }
+        // Leaving out 1, the only number with ω(n) = 0, as the main loop's histogram does
+        if let Some(num_prime_factors) = field(num_prime_factors_column)?
+            && num_prime_factors > 0
+        {
+            let bin = num_prime_factors as usize;
+            if bin >= num_prime_factors_histogram.len() {
+                num_prime_factors_histogram.resize(bin + 1, 0);
+            }
+            num_prime_factors_histogram[bin] += 1;
+        }
+        if let Some(num_factors) = field(num_factors_column)? {
+            num_factors_sum += u128::from(num_factors);
+        }
+    }
+
+    Ok(Stats {
+        numbers: count,
+        // Only the based numbers if the CSV has every number, rather than a filtered subset
+        based_numbers: (every_number && basedness_column.is_some()).then(|| based.len()),
+        largest_basedness,
+        num_prime_factors_histogram: num_prime_factors_column.map(|_| {
+            num_prime_factors_histogram
+                .into_iter()
+                .enumerate()
+                // Remove histogram bins which are unused, including the ω(n) = 0 bin
+                .filter(|&(_, n)| n > 0)
+                .collect()
+        }),
+        mean_num_factors: (num_factors_column.is_some() && count > 0)
+            .then(|| num_factors_sum as f64 / count as f64),
+    })
+}
+
+/// Prints aggregate statistics of a CSV of number info, in a single pass over it.
+fn stats(path: &PathBuf) -> Result<(), Box<dyn Error>> {
+    let stats = collect_stats(std::fs::File::open(path)?)?;
+
+    println!("Numbers: {}", stats.numbers);
+    if let Some(based_numbers) = stats.based_numbers {
+        println!("Based numbers: {based_numbers}");
+    }
+    match stats.largest_basedness {
+        Some((basedness, Some(number))) => {
+            println!("Largest basedness: {basedness} (first reached by {number})")
+        }
+        Some((basedness, None)) => println!("Largest basedness: {basedness}"),
+        None => {}
+    }
+    if let Some(histogram) = stats.num_prime_factors_histogram {
+        println!("Prime factor histogram:");
+        println!("{histogram:?}");
+    }
+    if let Some(mean) = stats.mean_num_factors {
+        println!("Mean d(n): {mean:.3}");
+    }
+    Ok(())
+}
+
+fn parse_record_kind(s: &str) -> Result<RecordKind, String> {
+    match s {
+        "basedness" => Ok(RecordKind::Basedness),
+        "hcn" => Ok(RecordKind::HighlyComposite),
+        "abundant" => Ok(RecordKind::Abundant),
+        _ => Err(format!(
+            "unknown record sequence {s:?}, expected basedness, hcn or abundant"
+        )),
+    }
+}
+
+/// Prints each of the given sequences of record-setting numbers up to `max_num`.
+fn print_records(max_num: u64, kinds: &[RecordKind]) {
+    let n = (max_num + 1) as usize;
+    let sequences = records_below(&mut SpfSieve::new(n), n, kinds);
+
+    if let Some(based) = sequences.based {
+        println!("Based numbers:");
+        println!("{:?}", based.as_slice());
+    }
+    if let Some(highly_composite) = sequences.highly_composite {
+        println!("Highly composite numbers (number, d(n)):");
+        println!("{:?}", highly_composite.as_slice());
+    }
+    if let Some(abundant) = sequences.abundant {
+        println!("Superabundant numbers (number, σ(n)):");
+        println!(
+            "{:?}",
+            abundant
+                .as_slice()
+                .iter()
+                .map(|(number, abundancy)| (number, abundancy.sum_of_divisors))
+                .collect::<Vec<_>>()
+        );
+    }
+}
+
+/// Compares the based numbers up to `max_num` with those listed in `reference`,
+/// returning an error describing the first difference, if any.
+fn verify_sequence(reference: &PathBuf, max_num: u64) -> Result<(), Box<dyn Error>> {
+    let mut expected = Vec::new();
+    for line in std::fs::read_to_string(reference)?.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        // In a b-file, the number follows its index
+        let number = line.split_whitespace().next_back().unwrap_or(line);
+        let number: u64 = number
+            .parse()
+            .map_err(|e| format!("invalid number {line:?} in {}: {e}", reference.display()))?;
+        if number <= max_num {
+            expected.push(number);
+        }
+    }
+
+    let n = (max_num + 1) as usize;
+    let based = based_numbers_below(&mut SpfSieve::new(n), n);
+    let actual: Vec<_> = based.as_slice().iter().map(|&(number, _)| number).collect();
+
+    if let Some(i) =
+        (0..expected.len().max(actual.len())).find(|&i| expected.get(i) != actual.get(i))
+    {
+        let show = |x: Option<&u64>| x.map_or("nothing".to_string(), u64::to_string);
+        return Err(format!(
+            "based number #{} up to {max_num} differs: expected {}, calculated {}",
+            i + 1,
+            show(expected.get(i)),
+            show(actual.get(i))
+        )
+        .into());
+    }
+
+    println!(
+        "All {} based numbers up to {max_num} match {}",
+        based.len(),
+        reference.display()
+    );
+    Ok(())
+}
+
+fn rank(number: u64) {
+    let mut below = None;
+
+    for (rank, (based, basedness)) in (1..).zip(based_numbers()) {
+        match based.cmp(&number) {
+            std::cmp::Ordering::Less => below = Some((rank, based, basedness)),
+            std::cmp::Ordering::Equal => {
+                println!("{number} is based number #{rank}, with basedness {basedness}");
+                return;
+            }
+            std::cmp::Ordering::Greater => {
+                println!("{number} is not a based number");
+                if let Some((rank, below, basedness)) = below {
+                    println!("Based number below: #{rank}: {below}, with basedness {basedness}");
+                }
+                println!("Based number above: #{rank}: {based}, with basedness {basedness}");
+                return;
+            }
+        }
+    }
+}
+
+/// Formats a factorization as a product of prime powers, like `2^3*3*5^2`. The empty
+/// factorization (of 1) is formatted as `1`.
+fn format_factorization(factors: &FactorMultiset) -> String {
+    if factors.is_empty() {
+        return "1".to_string();
+    }
+
+    factors
+        .iter()
+        .map(|(p, &k)| match k {
+            1 => p.to_string(),
+            k => format!("{p}^{k}"),
+        })
+        .collect::<Vec<_>>()
+        .join("*")
+}
+
+fn print_fixtures() -> Result<(), Box<dyn Error>> {
+    let mut wtr = Writer::from_writer(std::io::stdout());
+    for number in FIXTURE_NUMBERS {
+        let factors = factorize(number);
+        wtr.serialize(NumProperties {
+            number: number as u64,
+            num_factors: num_factors(&factors),
+            num_prime_factors: factors.len() as u64,
+            basedness: basedness(number),
+        })?;
+    }
+    wtr.flush()?;
     Ok(())
 }
 
-fn progress_bar<T>(iter: impl ExactSizeIterator<Item = T>) -> impl Iterator<Item = T> {
+/// Parses a number to factorize: any but 0, which has no factorization (1 has an empty one).
+fn parse_positive(s: &str) -> Result<usize, String> {
+    match s.parse() {
+        Ok(0) => Err("0 has no prime factorization".to_string()),
+        Ok(number) => Ok(number),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn print_factorization(number: usize) {
+    let factors = factorize(number);
+    println!(
+        "{number}: {factors:?} d(n)={} ω(n)={} basedness={}",
+        num_factors(&factors),
+        factors.len(),
+        basedness(number),
+    );
+}
+
+/// Prints a progress message from the main loop without disturbing the progress bar,
+/// or logs it at info level if --log started logging.
+fn report(pb: &ProgressBar, message: Arguments) {
+    pb.suspend(|| {
+        if logging::initialized() {
+            logging::log(Level::Info, message);
+        } else {
+            println!("{message}");
+        }
+    });
+}
+
+fn progress_bar<I: ExactSizeIterator>(iter: I) -> ProgressBarIter<I> {
     let pb = ProgressBar::new(iter.len() as u64);
 
     pb.set_style(
@@ -172,3 +1894,218 @@ fn progress_bar<T>(iter: impl ExactSizeIterator<Item = T>) -> impl Iterator<Item
 
     iter.progress_with(pb)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The number info of 1 to 10, as --output-csv writes it.
+    const NUMBERS_CSV: &str = "\
+number,num_factors,num_prime_factors,basedness
+1,1,0,0
+2,2,1,1
+3,2,1,2
+4,3,1,2
+5,2,1,3
+6,4,2,4
+7,2,1,4
+8,4,1,2
+9,3,1,4
+10,4,2,6
+";
+
+    /// `NUMBERS_CSV` with only the given columns.
+    fn with_columns(columns: &[&str]) -> String {
+        let mut lines = NUMBERS_CSV
+            .lines()
+            .map(|line| line.split(',').collect::<Vec<_>>());
+        let headers = lines.next().unwrap();
+        let kept: Vec<usize> = columns
+            .iter()
+            .map(|column| headers.iter().position(|h| h == column).unwrap())
+            .collect();
+        std::iter::once(headers)
+            .chain(lines)
+            .map(|fields| {
+                kept.iter()
+                    .map(|&i| fields[i])
+                    .collect::<Vec<_>>()
+                    .join(",")
+                    + "\n"
+            })
+            .collect()
+    }
+
+    #[test]
+    fn stats_of_every_column() {
+        let stats = collect_stats(NUMBERS_CSV.as_bytes()).unwrap();
+        assert_eq!(
+            stats,
+            Stats {
+                numbers: 10,
+                based_numbers: Some(5),
+                largest_basedness: Some((6, Some(10))),
+                num_prime_factors_histogram: Some(vec![(1, 7), (2, 2)]),
+                mean_num_factors: Some(2.7),
+            }
+        );
+    }
+
+    #[test]
+    fn stats_leave_out_missing_columns() {
+        let stats = collect_stats(with_columns(&["basedness"]).as_bytes()).unwrap();
+        assert_eq!(
+            stats,
+            Stats {
+                numbers: 10,
+                largest_basedness: Some((6, None)),
+                ..Stats::default()
+            }
+        );
+
+        let stats = collect_stats(with_columns(&["number", "num_factors"]).as_bytes()).unwrap();
+        assert_eq!(
+            stats,
+            Stats {
+                numbers: 10,
+                mean_num_factors: Some(2.7),
+                ..Stats::default()
+            }
+        );
+
+        let stats = collect_stats(with_columns(&["num_prime_factors"]).as_bytes()).unwrap();
+        assert_eq!(
+            stats,
+            Stats {
+                numbers: 10,
+                num_prime_factors_histogram: Some(vec![(1, 7), (2, 2)]),
+                ..Stats::default()
+            }
+        );
+    }
+
+    #[test]
+    fn stats_of_no_rows() {
+        let stats = collect_stats(with_columns(&["number"]).lines().next().unwrap().as_bytes());
+        assert_eq!(stats.unwrap(), Stats::default());
+    }
+
+    #[test]
+    fn stats_of_a_filtered_csv_leave_out_the_based_numbers() {
+        // As written with --basedness-min 3
+        let filtered: String = NUMBERS_CSV
+            .lines()
+            .enumerate()
+            .filter(|&(i, line)| {
+                i == 0 || line.rsplit(',').next().unwrap().parse::<u64>().unwrap() >= 3
+            })
+            .map(|(_, line)| format!("{line}\n"))
+            .collect();
+        let stats = collect_stats(filtered.as_bytes()).unwrap();
+        assert_eq!(stats.numbers, 5);
+        assert_eq!(stats.based_numbers, None);
+        assert_eq!(stats.largest_basedness, Some((6, Some(10))));
+    }
+
+    #[test]
+    fn factor_rejects_zero() {
+        assert_eq!(parse_positive("12"), Ok(12));
+        assert!(parse_positive("0").is_err());
+        assert!(parse_positive("x").is_err());
+        assert!(Args::try_parse_from(["based-num", "factor", "0"]).is_err());
+        assert!(Args::try_parse_from(["based-num", "factor", "1"]).is_ok());
+    }
+
+    #[test]
+    fn spf_sieve_limit_is_an_error_rather_than_a_panic() {
+        let limit = u64::from(u32::MAX) - 1;
+        assert!(check_spf_sieve_limit(limit, "records").is_ok());
+        let error = check_spf_sieve_limit(limit + 1, "records").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "records supports a maximum number of at most {limit}, not {}",
+                limit + 1
+            )
+        );
+
+        let args = Args::try_parse_from(["based-num", "--no-clone", "5000000000"]).unwrap();
+        assert!(
+            run(args)
+                .unwrap_err()
+                .to_string()
+                .contains(&limit.to_string())
+        );
+    }
+
+    /// A path in the temporary directory for a file written by a test, unique to the test.
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("based-num-test-{}-{name}", std::process::id()))
+    }
+
+    /// Runs the main command with the given arguments.
+    fn run_with(args: &[&str]) {
+        run(
+            Args::try_parse_from(std::iter::once("based-num").chain(args.iter().copied())).unwrap(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn dropping_the_output_thread_writes_everything_sent() {
+        let path = temp_path("parallel-output.csv");
+        let mut output_thread = spawn_output_thread(&path, vec![Column::Number]).unwrap();
+        for number in 1..=1000 {
+            output_thread.send(NumProperties {
+                number,
+                num_factors: 0,
+                num_prime_factors: 0,
+                basedness: 0,
+            });
+        }
+        // As when a run returns early with an error, rather than finishing it
+        drop(output_thread);
+
+        let written = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(written.lines().count(), 1001);
+        assert!(written.ends_with("\n1000\n"));
+    }
+
+    #[test]
+    fn resuming_matches_a_full_run() {
+        let resume_csv = temp_path("resume.csv");
+        run_with(&["600", "--output-csv", resume_csv.to_str().unwrap()]);
+
+        for extra in [&[][..], &["--stop-at-basedness", "30"]] {
+            let counts = [
+                temp_path("full-counts.csv"),
+                temp_path("resumed-counts.csv"),
+            ];
+            for (counts, resume) in counts.iter().zip([None, Some(&resume_csv)]) {
+                let mut args = vec!["2000", "--interval", "100"];
+                args.extend(["--counts-csv", counts.to_str().unwrap()]);
+                args.extend(extra);
+                if let Some(resume) = resume {
+                    args.extend(["--resume-from-csv", resume.to_str().unwrap()]);
+                }
+                run_with(&args);
+            }
+
+            let [full, resumed] = counts
+                .each_ref()
+                .map(|path| std::fs::read_to_string(path).unwrap());
+            counts
+                .iter()
+                .for_each(|path| std::fs::remove_file(path).unwrap());
+            assert_eq!(resumed, full, "with {extra:?}");
+            // The counts of based numbers grow within the resumed range
+            assert!(full.contains("\n100,25,9\n"), "{full}");
+            if !extra.is_empty() {
+                // Stopping at 145, the first number with basedness at least 30
+                assert!(full.ends_with("\n145,34,10\n"), "{full}");
+            }
+        }
+        std::fs::remove_file(resume_csv).unwrap();
+    }
+}
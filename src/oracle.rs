@@ -0,0 +1,95 @@
+//! Slow but obviously correct implementations of the arithmetic functions, straight
+//! from their definitions, for testing the optimized paths against. Only built for tests.
+
+/// Whether `n` is prime, by checking for any divisor between 2 and `sqrt(n)`.
+pub fn is_prime(n: usize) -> bool {
+    n >= 2
+        && (2..)
+            .take_while(|d| d * d <= n)
+            .all(|d| !n.is_multiple_of(d))
+}
+
+/// The prime factorization of `n`, as `(prime, power)` pairs in increasing order.
+pub fn factorization(n: usize) -> Vec<(usize, u8)> {
+    (2..=n)
+        .filter(|&p| n.is_multiple_of(p) && is_prime(p))
+        .map(|p| {
+            let mut power = 0;
+            let mut m = n;
+            while m.is_multiple_of(p) {
+                m /= p;
+                power += 1;
+            }
+            (p, power)
+        })
+        .collect()
+}
+
+/// All divisors of `n`, in increasing order, by checking every number up to `n`.
+pub fn divisors(n: usize) -> Vec<u64> {
+    (1..=n)
+        .filter(|&d| n.is_multiple_of(d))
+        .map(|d| d as u64)
+        .collect()
+}
+
+/// d(n), the number of divisors of `n`.
+pub fn num_factors(n: usize) -> u64 {
+    divisors(n).len() as u64
+}
+
+/// σ(n), the sum of the divisors of `n`.
+pub fn sum_of_divisors(n: usize) -> u64 {
+    divisors(n).iter().sum()
+}
+
+/// ω(n), the number of distinct primes dividing `n`.
+pub fn num_prime_factors(n: usize) -> u64 {
+    (2..=n)
+        .filter(|&p| n.is_multiple_of(p) && is_prime(p))
+        .count() as u64
+}
+
+/// φ(n), how many numbers from 1 to `n` have no common factor with `n` but 1.
+pub fn totient(n: usize) -> u64 {
+    (1..=n).filter(|&k| gcd(k, n) == 1).count() as u64
+}
+
+/// The greatest common divisor of `a` and `b`, by Euclid's algorithm.
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+/// μ(n): 0 if `n` is divisible by a square larger than 1, and otherwise -1 to the power
+/// of the number of primes dividing `n`.
+pub fn mobius(n: usize) -> i8 {
+    if (2..)
+        .take_while(|d| d * d <= n)
+        .any(|d| n.is_multiple_of(d * d))
+    {
+        0
+    } else if num_prime_factors(n).is_multiple_of(2) {
+        1
+    } else {
+        -1
+    }
+}
+
+/// The basedness of `n`: ω(n) · d(n - 1), or 0 for `n` of 0 or 1.
+pub fn basedness(n: usize) -> u64 {
+    if n <= 1 {
+        0
+    } else {
+        num_prime_factors(n) * num_factors(n - 1)
+    }
+}
+
+/// The based numbers less than `n` - those more based than every smaller number
+/// from 2 - as `(number, basedness)` pairs.
+pub fn based_numbers_below(n: usize) -> Vec<(u64, u64)> {
+    let basedness: Vec<_> = (0..n).map(basedness).collect();
+    (2..n)
+        .filter(|&i| basedness[2..i].iter().all(|&b| b < basedness[i]))
+        .map(|i| (i as u64, basedness[i]))
+        .collect()
+}
@@ -0,0 +1,164 @@
+//! Just enough of a PNG encoder to draw a bar chart, for --histogram-png: 8-bit
+//! grayscale images in uncompressed ("stored") deflate blocks, so that it needs no
+//! compression library. A chart is only a few hundred pixels across anyway.
+
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+const BACKGROUND: u8 = 0xff;
+const BAR: u8 = 0x40;
+const AXIS: u8 = 0x00;
+
+/// The width of each bar, in pixels.
+const BAR_WIDTH: usize = 24;
+/// The gap between bars, in pixels.
+const GAP: usize = 8;
+/// The height of the tallest bar, in pixels.
+const CHART_HEIGHT: usize = 240;
+/// The space around the chart, in pixels.
+const MARGIN: usize = 16;
+
+/// Draws a bar chart with a bar for each of `values` from left to right, scaled so that
+/// the largest value fills the height of the chart, and writes it as a PNG to `path`.
+/// Bars for nonzero values are always at least a pixel tall, so they can be seen.
+pub fn write_bar_chart(path: &Path, values: &[u64]) -> io::Result<()> {
+    let width = 2 * MARGIN + values.len() * (BAR_WIDTH + GAP) + GAP;
+    let height = 2 * MARGIN + CHART_HEIGHT + 1;
+    let max = values.iter().copied().max().unwrap_or(0).max(1);
+
+    let mut pixels = vec![BACKGROUND; width * height];
+    // The x axis, under the bars
+    let axis_row = MARGIN + CHART_HEIGHT;
+    pixels[axis_row * width + MARGIN..axis_row * width + width - MARGIN].fill(AXIS);
+    for (i, &value) in values.iter().enumerate() {
+        let bar_height = if value == 0 {
+            0
+        } else {
+            ((value as u128 * CHART_HEIGHT as u128 / max as u128) as usize).max(1)
+        };
+        let left = MARGIN + GAP + i * (BAR_WIDTH + GAP);
+        for row in axis_row - bar_height..axis_row {
+            pixels[row * width + left..row * width + left + BAR_WIDTH].fill(BAR);
+        }
+    }
+
+    let mut out = BufWriter::new(File::create(path)?);
+    write_grayscale(&mut out, width, height, &pixels)?;
+    out.flush()
+}
+
+/// Writes an 8-bit grayscale PNG of the given size, from `pixels` in rows from the top.
+fn write_grayscale(
+    out: &mut impl Write,
+    width: usize,
+    height: usize,
+    pixels: &[u8],
+) -> io::Result<()> {
+    debug_assert_eq!(pixels.len(), width * height);
+    out.write_all(b"\x89PNG\r\n\x1a\n")?;
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    // A bit depth of 8, grayscale, deflate, the only filter method, and no interlacing
+    header.extend_from_slice(&[8, 0, 0, 0, 0]);
+    write_chunk(out, b"IHDR", &header)?;
+
+    // Each row starts with its filter type, which is always 0 (none)
+    let mut raw = Vec::with_capacity(height * (width + 1));
+    for row in pixels.chunks_exact(width) {
+        raw.push(0);
+        raw.extend_from_slice(row);
+    }
+    write_chunk(out, b"IDAT", &zlib_stored(&raw))?;
+
+    write_chunk(out, b"IEND", &[])
+}
+
+/// Writes a PNG chunk: its length, type, data and the CRC of its type and data.
+fn write_chunk(out: &mut impl Write, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    out.write_all(&(data.len() as u32).to_be_bytes())?;
+    out.write_all(kind)?;
+    out.write_all(data)?;
+    let crc = crc32(kind.iter().chain(data).copied());
+    out.write_all(&crc.to_be_bytes())
+}
+
+/// `data` as a zlib stream of stored deflate blocks, each holding up to 65535 bytes.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    const MAX_BLOCK: usize = u16::MAX as usize;
+
+    // Deflate with a 32K window, using no compression
+    let mut out = vec![0x78, 0x01];
+    let mut blocks = data.chunks(MAX_BLOCK).peekable();
+    if blocks.peek().is_none() {
+        // A final empty block
+        out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let is_final = blocks.peek().is_none();
+        let len = block.len() as u16;
+        out.push(u8::from(is_final));
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+/// The CRC-32 (as used by PNG, and by gzip) of `bytes`.
+fn crc32(bytes: impl Iterator<Item = u8>) -> u32 {
+    let mut crc = !0u32;
+    for byte in bytes {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xedb8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+/// The Adler-32 checksum of `bytes`, which ends a zlib stream.
+fn adler32(bytes: &[u8]) -> u32 {
+    const MOD: u32 = 65521;
+    let (mut a, mut b) = (1u32, 0u32);
+    // Sums of this many bytes can't overflow before being reduced
+    for chunk in bytes.chunks(5552) {
+        for &byte in chunk {
+            a += u32::from(byte);
+            b += a;
+        }
+        a %= MOD;
+        b %= MOD;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksums_match_known_values() {
+        assert_eq!(crc32(b"123456789".iter().copied()), 0xcbf4_3926);
+        assert_eq!(crc32(b"IEND".iter().copied()), 0xae42_6082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+        assert_eq!(adler32(&[]), 1);
+    }
+
+    #[test]
+    fn zlib_stored_splits_into_blocks() {
+        let data = vec![7; 70_000];
+        let stream = zlib_stored(&data);
+        // The header, two block headers, the data and the checksum
+        assert_eq!(stream.len(), 2 + 2 * 5 + data.len() + 4);
+        assert_eq!(&stream[2..7], &[0, 0xff, 0xff, 0, 0]);
+        let second = 7 + u16::MAX as usize;
+        assert_eq!(&stream[second..second + 5], &[1, 0x71, 0x11, 0x8e, 0xee]);
+        assert_eq!(stream[stream.len() - 4..], adler32(&data).to_be_bytes());
+    }
+}
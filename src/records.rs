@@ -0,0 +1,361 @@
+//! Detection of record-setting numbers, such as the based numbers - numbers
+//! which are more based than all smaller numbers.
+
+use std::{collections::VecDeque, num::NonZeroUsize};
+
+use crate::{
+    factor::{Abundancy, factorize_with_primes, num_factors, sum_of_divisors},
+    sieve::Sieve,
+};
+
+/// The record-setting numbers of a sequence of values, along with the value
+/// which set each record.
+#[derive(Clone, Debug, Default)]
+pub struct Records<T = u64> {
+    records: Vec<(u64, T)>,
+}
+
+impl<T: Ord + Copy> Records<T> {
+    /// Creates a new, empty list of records.
+    pub fn new() -> Self {
+        Self {
+            records: Vec::new(),
+        }
+    }
+
+    /// Considers `number` with the given `value`, recording it if its value beats
+    /// that of all previous records (the first number considered is always a record).
+    /// Numbers should be considered in increasing order.
+    ///
+    /// Returns whether or not `number` set a new record.
+    pub fn consider(&mut self, number: u64, value: T) -> bool {
+        let is_record = self.last().is_none_or(|(_, last)| value > last);
+        if is_record {
+            self.records.push((number, value));
+        }
+        is_record
+    }
+
+    /// The most recent record, if any.
+    pub fn last(&self) -> Option<(u64, T)> {
+        self.records.last().copied()
+    }
+
+    /// The records so far, as `(number, value)` pairs in increasing order.
+    pub fn as_slice(&self) -> &[(u64, T)] {
+        &self.records
+    }
+
+    /// The number of records so far.
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Whether or not there have been any records so far.
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+}
+
+/// The local minima of a sequence of values: the numbers whose values are smaller than
+/// those of the `window` numbers considered before them and the `window` numbers
+/// considered after them, along with their values.
+///
+/// A number is only a minimum once the `window` numbers after it have been considered,
+/// so the last `window` numbers considered are undecided, and never appear. Neither do
+/// the first `window`, which don't have a full window before them.
+#[derive(Clone, Debug)]
+pub struct WindowMinima<T = u64> {
+    window: usize,
+    /// The last `2 * window + 1` numbers considered, once that many have been
+    recent: VecDeque<(u64, T)>,
+    minima: Vec<(u64, T)>,
+}
+
+impl<T: Ord + Copy> WindowMinima<T> {
+    /// Creates a new, empty list of minima over the given window.
+    pub fn new(window: NonZeroUsize) -> Self {
+        Self {
+            window: window.get(),
+            recent: VecDeque::with_capacity(2 * window.get() + 2),
+            minima: Vec::new(),
+        }
+    }
+
+    /// Considers `number` with the given `value`, which decides whether the number
+    /// considered `window` numbers ago is a minimum. Numbers should be considered in
+    /// increasing order. Takes time proportional to the window.
+    ///
+    /// Returns the newly decided minimum, if there is one.
+    pub fn consider(&mut self, number: u64, value: T) -> Option<(u64, T)> {
+        if self.recent.len() == 2 * self.window + 1 {
+            self.recent.pop_front();
+        }
+        self.recent.push_back((number, value));
+        if self.recent.len() < 2 * self.window + 1 {
+            return None;
+        }
+
+        let middle = self.recent[self.window];
+        let is_minimum = self
+            .recent
+            .iter()
+            .enumerate()
+            .all(|(i, &(_, other))| i == self.window || middle.1 < other);
+        if is_minimum {
+            self.minima.push(middle);
+            Some(middle)
+        } else {
+            None
+        }
+    }
+
+    /// The minima so far, as `(number, value)` pairs in increasing order.
+    pub fn as_slice(&self) -> &[(u64, T)] {
+        &self.minima
+    }
+
+    /// The number of minima so far.
+    pub fn len(&self) -> usize {
+        self.minima.len()
+    }
+
+    /// Whether or not there have been any minima so far.
+    pub fn is_empty(&self) -> bool {
+        self.minima.is_empty()
+    }
+}
+
+/// An unbounded, lazy iterator over the based numbers, as `(number, basedness)`
+/// pairs. Created by [`based_numbers`].
+///
+/// Unlike a sieve, this doesn't need a maximum number up front, and uses memory
+/// proportional only to the number of primes found so far - at the cost of
+/// factorizing each number by trial division.
+#[derive(Clone, Debug)]
+pub struct BasedNumbers {
+    primes: Vec<usize>,
+    n: usize,
+    prev_num_factors: u64,
+    records: Records,
+}
+
+/// A lazy iterator over the based numbers - numbers which are more based than
+/// all smaller numbers.
+pub fn based_numbers() -> BasedNumbers {
+    BasedNumbers {
+        primes: Vec::new(),
+        n: 1,
+        // d(1) = 1
+        prev_num_factors: 1,
+        records: Records::new(),
+    }
+}
+
+/// The largest based number no larger than `x`, along with its basedness, if any.
+pub fn largest_based_at_most(x: u64) -> Option<(u64, u64)> {
+    based_numbers()
+        .take_while(|&(number, _)| number <= x)
+        .last()
+}
+
+/// The based numbers less than `n`, found with `sieve` (which must be able to factorize
+/// every number less than `n`, and must not have factorized any yet). Much faster than
+/// [`based_numbers`] when the maximum is known up front.
+pub fn based_numbers_below(sieve: &mut dyn Sieve, n: usize) -> Records {
+    let mut based = Records::new();
+    // d(1) = 1
+    let mut prev_num_factors = 1;
+    for i in 2..n {
+        let factors = sieve.factorize(i);
+        based.consider(i as u64, factors.len() as u64 * prev_num_factors);
+        prev_num_factors = num_factors(factors);
+    }
+    based
+}
+
+/// A sequence of record-setting numbers which [`records_below`] can find.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RecordKind {
+    /// The based numbers, which are more based than all smaller numbers.
+    Basedness,
+    /// The highly composite numbers, which have more divisors than all smaller numbers.
+    HighlyComposite,
+    /// The superabundant numbers, whose abundancy σ(n) / n is larger than that of all
+    /// smaller numbers.
+    Abundant,
+}
+
+/// Several sequences of record-setting numbers, found together by [`records_below`].
+/// Each sequence is only present if it was asked for.
+#[derive(Clone, Debug, Default)]
+pub struct RecordSequences {
+    /// The based numbers, with their basedness.
+    pub based: Option<Records>,
+    /// The highly composite numbers, with their number of divisors d(n).
+    pub highly_composite: Option<Records>,
+    /// The superabundant numbers, with their abundancy.
+    pub abundant: Option<Records<Abundancy>>,
+}
+
+/// The record-setting numbers less than `n` of each of the given `kinds`, found in a
+/// single pass with `sieve` (which, as for [`based_numbers_below`], must be able to
+/// factorize every number less than `n`, and must not have factorized any yet).
+/// Finding several sequences this way only factorizes each number once.
+pub fn records_below(sieve: &mut dyn Sieve, n: usize, kinds: &[RecordKind]) -> RecordSequences {
+    let wants = |kind| kinds.contains(&kind);
+    let mut sequences = RecordSequences {
+        based: wants(RecordKind::Basedness).then(Records::new),
+        highly_composite: wants(RecordKind::HighlyComposite).then(Records::new),
+        abundant: wants(RecordKind::Abundant).then(Records::new),
+    };
+
+    // 1 has no prime factors, so it isn't based, but it starts the other sequences
+    if n > 1 {
+        // d(1) = 1
+        if let Some(highly_composite) = &mut sequences.highly_composite {
+            highly_composite.consider(1, 1);
+        }
+        // σ(1) = 1
+        if let Some(abundant) = &mut sequences.abundant {
+            abundant.consider(
+                1,
+                Abundancy {
+                    sum_of_divisors: 1,
+                    number: 1,
+                },
+            );
+        }
+    }
+
+    // d(1) = 1
+    let mut prev_num_factors = 1;
+    for i in 2..n {
+        let factors = sieve.factorize(i);
+        let num_factors = num_factors(factors);
+
+        if let Some(based) = &mut sequences.based {
+            based.consider(i as u64, factors.len() as u64 * prev_num_factors);
+        }
+        if let Some(highly_composite) = &mut sequences.highly_composite {
+            highly_composite.consider(i as u64, num_factors);
+        }
+        if let Some(abundant) = &mut sequences.abundant {
+            abundant.consider(
+                i as u64,
+                Abundancy {
+                    sum_of_divisors: sum_of_divisors(factors),
+                    number: i as u64,
+                },
+            );
+        }
+
+        prev_num_factors = num_factors;
+    }
+    sequences
+}
+
+impl Iterator for BasedNumbers {
+    type Item = (u64, u64);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.n += 1;
+
+            let factors = factorize_with_primes(self.n, &self.primes);
+            let num_factors = num_factors(&factors);
+
+            // All prime numbers have 2 factors: 1 and itself
+            if num_factors == 2 {
+                self.primes.push(self.n);
+            }
+
+            let basedness = factors.len() as u64 * self.prev_num_factors;
+            self.prev_num_factors = num_factors;
+
+            if self.records.consider(self.n as u64, basedness) {
+                return self.records.last();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{
+        oracle,
+        sieve::{CloneSieve, SpfSieve},
+    };
+
+    #[test]
+    fn based_numbers_match_oracle() {
+        let n = 600;
+        let expected = oracle::based_numbers_below(n);
+        assert_eq!(
+            based_numbers_below(&mut SpfSieve::new(n), n).as_slice(),
+            expected
+        );
+        assert_eq!(
+            based_numbers()
+                .take_while(|&(number, _)| number < n as u64)
+                .collect::<Vec<_>>(),
+            expected
+        );
+    }
+
+    #[test]
+    fn scalar_based_records_match_full_properties_array() {
+        // Keeping d(n) for every number, and looking up d(n - 1) in it
+        let n = 100_000;
+        let mut sieve = CloneSieve::new(n);
+        let mut num_factors_of = vec![0, 1];
+        let mut expected = Records::new();
+        for i in 2..n {
+            let factors = sieve.factorize(i);
+            num_factors_of.push(num_factors(factors));
+            expected.consider(i as u64, factors.len() as u64 * num_factors_of[i - 1]);
+        }
+
+        let based = based_numbers_below(&mut CloneSieve::new(n), n);
+        assert_eq!(based.as_slice(), expected.as_slice());
+    }
+
+    #[test]
+    fn largest_based_at_most_matches_known_prefix() {
+        // The based numbers up to 100 are 2, 3, 5, 6, 10, 15, 21, 55 and 85
+        assert_eq!(largest_based_at_most(0), None);
+        assert_eq!(largest_based_at_most(1), None);
+        assert_eq!(largest_based_at_most(2), Some((2, 1)));
+        assert_eq!(largest_based_at_most(9), Some((6, 4)));
+        assert_eq!(largest_based_at_most(54), Some((21, 12)));
+        assert_eq!(largest_based_at_most(55), Some((55, 16)));
+        assert_eq!(largest_based_at_most(144), Some((85, 24)));
+    }
+
+    #[test]
+    fn window_minima_match_brute_force() {
+        // The basedness of the composite numbers, as for --anti-based
+        let values: Vec<(u64, u64)> = (4..3000)
+            .filter(|&n| !oracle::is_prime(n))
+            .map(|n| (n as u64, oracle::basedness(n)))
+            .collect();
+
+        for window in [1, 2, 5, 20] {
+            let expected: Vec<_> = (window..values.len().saturating_sub(window))
+                .filter(|&i| (i - window..=i + window).all(|j| j == i || values[i].1 < values[j].1))
+                .map(|i| values[i])
+                .collect();
+            assert!(!expected.is_empty());
+
+            let mut minima = WindowMinima::new(NonZeroUsize::new(window).unwrap());
+            let decided: Vec<_> = values
+                .iter()
+                .filter_map(|&(number, value)| minima.consider(number, value))
+                .collect();
+            assert_eq!(minima.as_slice(), expected, "window {window}");
+            assert_eq!(decided, expected, "window {window}");
+        }
+    }
+}
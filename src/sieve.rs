@@ -0,0 +1,214 @@
+//! Sieves for factorizing every number up to some maximum.
+
+use crate::factor::FactorMultiset;
+
+/// Something which can factorize all numbers up to some maximum.
+pub trait Sieve {
+    /// The prime factorization of `i`.
+    ///
+    /// Every number from 2 up to `i` must have been factorized (in increasing order)
+    /// before `i` is. After that, any number which has already been factorized can
+    /// be factorized again.
+    fn factorize(&mut self, i: usize) -> &FactorMultiset;
+
+    /// The primes found so far, in increasing order. Some sieves find all primes
+    /// up to their maximum before factorizing anything.
+    fn primes(&self) -> &[usize];
+}
+
+/// A sieve which keeps the factorization of every number. The factorization of a
+/// composite `i` is found by cloning the factorization of `i / p` for its smallest
+/// prime factor `p`, which is found by trial division by the primes found so far.
+pub struct CloneSieve {
+    prime_factors: Vec<FactorMultiset>,
+    primes: Vec<usize>,
+    /// How many of `primes` were known up front, rather than found by the sieve
+    num_known: usize,
+    /// The index in `primes` of the next known prime which hasn't been reached yet
+    next_known: usize,
+    /// The next number to be factorized
+    next: usize,
+}
+
+impl CloneSieve {
+    /// Creates a sieve for numbers up to (but not including) `n`.
+    pub fn new(n: usize) -> Self {
+        Self::with_primes(n, Vec::new())
+    }
+
+    /// Creates a sieve for numbers up to (but not including) `n`, which starts out
+    /// knowing `primes`, such as the primes found by a previous run. Known primes are
+    /// factorized without trial division, which is most of the work of factorizing a
+    /// prime. Primes past the known ones are found as usual.
+    ///
+    /// `primes` must be every prime up to the largest of them, in increasing order -
+    /// otherwise, numbers will be factorized wrongly. Any primes which aren't less
+    /// than `n` are ignored.
+    pub fn with_primes(n: usize, mut primes: Vec<usize>) -> Self {
+        debug_assert!(
+            primes.windows(2).all(|w| w[0] < w[1]),
+            "known primes are not strictly increasing"
+        );
+        primes.truncate(primes.partition_point(|&p| p < n));
+
+        Self {
+            prime_factors: vec![FactorMultiset::new(); n],
+            num_known: primes.len(),
+            primes,
+            next_known: 0,
+            next: 2,
+        }
+    }
+}
+
+impl Sieve for CloneSieve {
+    fn factorize(&mut self, i: usize) -> &FactorMultiset {
+        if i < self.next {
+            return &self.prime_factors[i];
+        }
+        assert_eq!(
+            i, self.next,
+            "numbers must be factorized in increasing order"
+        );
+        self.next += 1;
+
+        let known = self.next_known < self.num_known && self.primes[self.next_known] == i;
+        let p = if known {
+            self.next_known += 1;
+            None
+        } else {
+            // Find a prime factor of i - it must necessarily be one of the primes we have
+            // already found, or i is itself a prime
+            self.primes
+                .iter()
+                .copied()
+                // If i is non-prime, then one of its factors must be no larger than sqrt(i)
+                .take_while(|p| p * p <= i)
+                .find(|p| i.is_multiple_of(*p))
+        };
+
+        // If we found some small (< i) prime factor p
+        if let Some(p) = p {
+            // All factors of i / p are also factors of i
+            self.prime_factors[i] = self.prime_factors[i / p].clone();
+
+            // The power of p in the prime factor representation of i is
+            // 1 + the power of p in the prime factor representation of i / p
+            self.prime_factors[i].increment(p);
+        // Otherwise, i must be a prime
+        } else {
+            self.prime_factors[i].insert(i, 1);
+            if !known {
+                self.primes.push(i);
+            }
+        }
+        self.prime_factors[i].finalize();
+
+        &self.prime_factors[i]
+    }
+
+    fn primes(&self) -> &[usize] {
+        &self.primes
+    }
+}
+
+/// A sieve which only keeps the smallest prime factor of every number. The factorization
+/// of `i` is found on demand by repeatedly dividing out smallest prime factors, into a
+/// single reused [`FactorMultiset`] - so no factorizations are cloned or kept around.
+pub struct SpfSieve {
+    smallest_prime_factors: Vec<u32>,
+    primes: Vec<usize>,
+    factors: FactorMultiset,
+}
+
+impl SpfSieve {
+    /// The largest `n` which [`new`](Self::new) accepts, since smallest prime factors
+    /// are kept as `u32`s.
+    pub const MAX_N: usize = u32::MAX as usize;
+
+    /// Creates a sieve for numbers up to (but not including) `n`, which must be
+    /// no larger than [`MAX_N`](Self::MAX_N).
+    pub fn new(n: usize) -> Self {
+        assert!(
+            n <= Self::MAX_N,
+            "SpfSieve only supports numbers up to u32::MAX"
+        );
+
+        let mut smallest_prime_factors = vec![0; n];
+        let mut primes = Vec::new();
+
+        // Linear sieve - every composite i * p is crossed out exactly once,
+        // by its smallest prime factor p
+        for i in 2..n {
+            if smallest_prime_factors[i] == 0 {
+                smallest_prime_factors[i] = i as u32;
+                primes.push(i);
+            }
+
+            let spf = smallest_prime_factors[i] as usize;
+            for &p in primes.iter().take_while(|&&p| p <= spf && i * p < n) {
+                smallest_prime_factors[i * p] = p as u32;
+            }
+        }
+
+        Self {
+            smallest_prime_factors,
+            primes,
+            factors: FactorMultiset::new(),
+        }
+    }
+}
+
+impl Sieve for SpfSieve {
+    fn factorize(&mut self, mut i: usize) -> &FactorMultiset {
+        self.factors.clear();
+
+        while i > 1 {
+            let p = self.smallest_prime_factors[i] as usize;
+            self.factors.increment(p);
+            i /= p;
+        }
+
+        &self.factors
+    }
+
+    fn primes(&self) -> &[usize] {
+        &self.primes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oracle;
+
+    const N: usize = 2000;
+
+    /// Checks that `sieve` factorizes every number below [`N`] correctly, and finds
+    /// every prime below it.
+    fn check_against_oracle(sieve: &mut dyn Sieve) {
+        for i in 2..N {
+            let factors: Vec<_> = sieve.factorize(i).iter().map(|(&p, &k)| (p, k)).collect();
+            assert_eq!(factors, oracle::factorization(i), "i = {i}");
+        }
+        let primes: Vec<_> = (2..N).filter(|&p| oracle::is_prime(p)).collect();
+        assert_eq!(sieve.primes(), primes);
+    }
+
+    #[test]
+    fn clone_sieve_matches_oracle() {
+        check_against_oracle(&mut CloneSieve::new(N));
+    }
+
+    #[test]
+    fn clone_sieve_with_known_primes_matches_oracle() {
+        // Only some of the primes are known, so the rest have to be found as usual
+        let known = (2..N / 2).filter(|&p| oracle::is_prime(p)).collect();
+        check_against_oracle(&mut CloneSieve::with_primes(N, known));
+    }
+
+    #[test]
+    fn spf_sieve_matches_oracle() {
+        check_against_oracle(&mut SpfSieve::new(N));
+    }
+}
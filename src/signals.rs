@@ -0,0 +1,32 @@
+//! Handling of SIGINT and SIGTERM, so that a long run can be stopped without
+//! losing everything it has calculated so far.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Installs handlers for SIGINT and SIGTERM. The first signal only sets a flag,
+/// to be checked with [`interrupted`]. A second signal exits immediately.
+pub fn install() {
+    for signal in [libc::SIGINT, libc::SIGTERM] {
+        // SAFETY: the handler is async-signal-safe - it only touches an atomic, and `_exit`s
+        unsafe {
+            libc::signal(
+                signal,
+                handle as extern "C" fn(libc::c_int) as libc::sighandler_t,
+            );
+        }
+    }
+}
+
+extern "C" fn handle(_signal: libc::c_int) {
+    if INTERRUPTED.swap(true, Ordering::Relaxed) {
+        // SAFETY: `_exit` doesn't run destructors or `atexit` handlers, so is safe in a handler
+        unsafe { libc::_exit(130) };
+    }
+}
+
+/// Whether or not a signal has been received since the handlers were installed.
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::Relaxed)
+}